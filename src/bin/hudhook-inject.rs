@@ -0,0 +1,104 @@
+use std::{env, ffi::OsString, path::PathBuf, process::ExitCode};
+
+use hudhook_mini::inject::{self, LaunchOptions, ProcessList, ProcessTarget, SystemProcessList};
+
+const USAGE: &str = "usage: hudhook-inject <dll> (--pid <pid> | --name <exe> | --window <title>)
+       hudhook-inject <dll> --launch <exe> [--cwd <dir>] [--env <key>=<value>]... [-- <args>...]";
+
+enum Mode {
+    Attach(ProcessTarget),
+    Launch(LaunchOptions),
+}
+
+fn parse_args(mut args: impl Iterator<Item = OsString>) -> Option<(PathBuf, Mode)> {
+    let dll_path = PathBuf::from(args.next()?);
+
+    let mode = match (args.next()?.to_str()?, args.next()?) {
+        ("--pid", pid) => Mode::Attach(ProcessTarget::Pid(pid.to_str()?.parse().ok()?)),
+        ("--name", name) => Mode::Attach(ProcessTarget::Name(name.into_string().ok()?)),
+        ("--window", title) => Mode::Attach(ProcessTarget::WindowTitle(title.into_string().ok()?)),
+        ("--launch", program) => {
+            let mut options = LaunchOptions {
+                program: PathBuf::from(program),
+                ..Default::default()
+            };
+
+            while let Some(arg) = args.next() {
+                match arg.to_str() {
+                    Some("--cwd") => options.current_dir = Some(PathBuf::from(args.next()?)),
+                    Some("--env") => {
+                        let var = args.next()?.into_string().ok()?;
+                        let (key, value) = var.split_once('=')?;
+                        options.env.push((key.into(), value.into()));
+                    }
+                    Some("--") => {
+                        options.args.extend(args.by_ref());
+                    }
+                    _ => return None,
+                }
+            }
+
+            return Some((dll_path, Mode::Launch(options)));
+        }
+        _ => return None,
+    };
+
+    if args.next().is_some() {
+        return None;
+    }
+
+    Some((dll_path, mode))
+}
+
+fn attach(
+    dll_path: PathBuf,
+    target: ProcessTarget,
+    list: &impl ProcessList,
+) -> hudhook_mini::error::Result<()> {
+    let pid = target.resolve(list)?;
+    let module = inject::inject(pid, &dll_path)?;
+
+    println!(
+        "Injected {} into process {pid} at {:#x}",
+        dll_path.display(),
+        module.0
+    );
+    Ok(())
+}
+
+fn launch(dll_path: PathBuf, options: LaunchOptions) -> hudhook_mini::error::Result<u32> {
+    let process = inject::launch(&options, &dll_path)?;
+
+    println!(
+        "Launched {} as process {} with {} at {:#x}",
+        options.program.display(),
+        process.pid(),
+        dll_path.display(),
+        process.module().0
+    );
+    process.wait()
+}
+
+fn main() -> ExitCode {
+    let Some((dll_path, mode)) = parse_args(env::args_os().skip(1)) else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    match mode {
+        Mode::Attach(target) => match attach(dll_path, target, &SystemProcessList) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Injection failed: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Mode::Launch(options) => match launch(dll_path, options) {
+            Ok(exit_code) => std::process::exit(exit_code as i32),
+            Err(e) => {
+                eprintln!("Launch failed: {e}");
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
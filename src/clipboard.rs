@@ -0,0 +1,206 @@
+use std::{ptr, slice};
+
+use imgui::ClipboardBackend;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use windows::Win32::{
+    Foundation::{GlobalFree, HANDLE, HGLOBAL},
+    System::{
+        DataExchange::{
+            CloseClipboard,
+            EmptyClipboard,
+            GetClipboardData,
+            OpenClipboard,
+            SetClipboardData,
+        },
+        Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE},
+        Ole::CF_UNICODETEXT,
+        Threading::Sleep,
+    },
+};
+
+const OPEN_RETRIES: u32 = 10;
+
+pub(crate) type ClipboardFactory = Box<dyn Fn() -> Box<dyn ClipboardBackend> + Send + Sync>;
+
+static CLIPBOARD_FACTORY: Lazy<Mutex<Option<ClipboardFactory>>> = Lazy::new(|| Mutex::new(None));
+
+pub(crate) fn factory<C: ClipboardBackend + Clone + Send + Sync>(backend: C) -> ClipboardFactory {
+    Box::new(move || Box::new(backend.clone()))
+}
+
+pub(crate) fn set_factory(factory: ClipboardFactory) {
+    *CLIPBOARD_FACTORY.lock() = Some(factory);
+}
+
+pub(crate) fn create_backend() -> DynClipboard {
+    match CLIPBOARD_FACTORY.lock().as_ref() {
+        Some(factory) => DynClipboard(factory()),
+        None => DynClipboard(Box::new(Win32Clipboard)),
+    }
+}
+
+pub(crate) struct DynClipboard(Box<dyn ClipboardBackend>);
+
+impl ClipboardBackend for DynClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.0.get()
+    }
+
+    fn set(&mut self, value: &str) {
+        self.0.set(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Win32Clipboard;
+
+impl Win32Clipboard {
+    unsafe fn open() -> bool {
+        for _ in 0..OPEN_RETRIES {
+            if OpenClipboard(None).is_ok() {
+                return true;
+            }
+            Sleep(1);
+        }
+        false
+    }
+}
+
+impl ClipboardBackend for Win32Clipboard {
+    fn get(&mut self) -> Option<String> {
+        unsafe {
+            if !Self::open() {
+                return None;
+            }
+
+            let text = GetClipboardData(CF_UNICODETEXT.0 as u32)
+                .ok()
+                .and_then(|handle| {
+                    let hglobal = HGLOBAL(handle.0 as _);
+                    let data = GlobalLock(hglobal) as *const u16;
+                    if data.is_null() {
+                        return None;
+                    }
+
+                    let len = GlobalSize(hglobal) / 2;
+                    let text = utf16_to_string(slice::from_raw_parts(data, len));
+                    let _ = GlobalUnlock(hglobal);
+                    Some(text)
+                });
+
+            let _ = CloseClipboard();
+            text
+        }
+    }
+
+    fn set(&mut self, value: &str) {
+        let units = string_to_utf16(value);
+
+        unsafe {
+            if !Self::open() {
+                return;
+            }
+
+            let _ = EmptyClipboard();
+
+            if let Ok(hglobal) = GlobalAlloc(GMEM_MOVEABLE, units.len() * 2) {
+                let data = GlobalLock(hglobal) as *mut u16;
+                if !data.is_null() {
+                    ptr::copy_nonoverlapping(units.as_ptr(), data, units.len());
+                    let _ = GlobalUnlock(hglobal);
+                }
+
+                if data.is_null()
+                    || SetClipboardData(CF_UNICODETEXT.0 as u32, HANDLE(hglobal.0 as _)).is_err()
+                {
+                    let _ = GlobalFree(hglobal);
+                }
+            }
+
+            let _ = CloseClipboard();
+        }
+    }
+}
+
+pub fn utf16_to_string(units: &[u16]) -> String {
+    let end = units.iter().position(|&u| u == 0).unwrap_or(units.len());
+    String::from_utf16_lossy(&units[..end]).replace("\r\n", "\n")
+}
+
+pub fn string_to_utf16(s: &str) -> Vec<u16> {
+    s.replace("\r\n", "\n")
+        .replace('\n', "\r\n")
+        .encode_utf16()
+        .chain(Some(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    #[test]
+    fn truncates_at_the_first_nul() {
+        let mut units = utf16("hello");
+        units.extend([0, b'x' as u16, 0]);
+
+        assert_eq!(utf16_to_string(&units), "hello");
+        assert_eq!(utf16_to_string(&[0, b'x' as u16]), "");
+        assert_eq!(utf16_to_string(&utf16("no terminator")), "no terminator");
+        assert_eq!(utf16_to_string(&[]), "");
+    }
+
+    #[test]
+    fn terminates_with_a_single_nul() {
+        let units = string_to_utf16("abc");
+
+        assert_eq!(units, [b'a' as u16, b'b' as u16, b'c' as u16, 0]);
+        assert_eq!(string_to_utf16(""), [0]);
+    }
+
+    #[test]
+    fn converts_line_endings() {
+        assert_eq!(
+            string_to_utf16("a\nb"),
+            [&utf16("a\r\nb")[..], &[0]].concat()
+        );
+        assert_eq!(
+            string_to_utf16("a\r\nb"),
+            [&utf16("a\r\nb")[..], &[0]].concat()
+        );
+        assert_eq!(utf16_to_string(&utf16("a\r\nb\r\n")), "a\nb\n");
+        assert_eq!(utf16_to_string(&utf16("a\rb")), "a\rb");
+    }
+
+    #[test]
+    fn round_trips_crlf() {
+        for text in ["one\ntwo\n", "one\r\ntwo", "\n\n", "no newline"] {
+            let round_trip = utf16_to_string(&string_to_utf16(text));
+
+            assert_eq!(round_trip, text.replace("\r\n", "\n"), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn round_trips_non_bmp_characters() {
+        let text = "clip \u{1F4CB} \u{10348}\n\u{FFFF}";
+        let units = string_to_utf16(text);
+
+        assert_eq!(&units[5..7], [0xD83D, 0xDCCB]);
+        assert_eq!(utf16_to_string(&units), text);
+    }
+
+    #[test]
+    fn replaces_unpaired_surrogates() {
+        assert_eq!(
+            utf16_to_string(&[b'a' as u16, 0xD83D, b'b' as u16, 0]),
+            "a\u{FFFD}b"
+        );
+        assert_eq!(utf16_to_string(&[0xDCCB]), "\u{FFFD}");
+    }
+}
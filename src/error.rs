@@ -0,0 +1,180 @@
+use std::{error, fmt, io, path::PathBuf};
+
+use imgui::TextureId;
+use minhook_raw::sys::MH_STATUS;
+
+use crate::Backend;
+
+pub type Result<T, E = HudhookError> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum HudhookError {
+    MinHook(MH_STATUS),
+    Windows(windows::core::Error),
+    Io(io::Error),
+    PipelineNotReady,
+    LockContention,
+    NoRenderLoop,
+    InvalidDisplaySize([f32; 2]),
+    TextureNotFound(TextureId),
+    TextureSizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    TextureDataLength {
+        expected: usize,
+        actual: usize,
+    },
+    InvalidRecording(String),
+    HookTargetNotFound(&'static str),
+    NoBackendDetected,
+    ProcessNotFound(String),
+    AmbiguousProcess {
+        target: String,
+        pids: Vec<u32>,
+    },
+    RemoteLoadFailed(PathBuf),
+    DrainTimeout(usize),
+    AlreadyApplied,
+    BackendInUse(Backend),
+    AmbiguousBackend,
+    BitnessMismatch,
+    WndProcSubclassed(usize),
+    ModuleNotLoaded(String),
+    ExportNotFound {
+        module: String,
+        export: String,
+    },
+    InvalidPattern(String),
+    PatternNotFound {
+        module: String,
+        pattern: String,
+    },
+    AmbiguousPattern {
+        pattern: String,
+        matches: usize,
+    },
+}
+
+impl fmt::Display for HudhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HudhookError::MinHook(status) => write!(f, "MinHook call failed: {status:?}"),
+            HudhookError::Windows(e) => write!(f, "Windows API call failed: {e}"),
+            HudhookError::Io(e) => write!(f, "I/O error: {e}"),
+            HudhookError::PipelineNotReady => write!(f, "render pipeline is not initialized yet"),
+            HudhookError::LockContention => write!(f, "render pipeline is busy on another thread"),
+            HudhookError::NoRenderLoop => write!(f, "no render loop is available for this window"),
+            HudhookError::InvalidDisplaySize([w, h]) => {
+                write!(f, "invalid display size {w}x{h}")
+            }
+            HudhookError::TextureNotFound(texture_id) => {
+                write!(f, "texture {} does not exist", texture_id.id())
+            }
+            HudhookError::TextureSizeMismatch {
+                expected: (ew, eh),
+                actual: (aw, ah),
+            } => {
+                write!(f, "texture is {ew}x{eh}, cannot replace it with {aw}x{ah}")
+            }
+            HudhookError::TextureDataLength {
+                expected,
+                actual,
+            } => {
+                write!(f, "texture data is {actual} bytes, expected {expected}")
+            }
+            HudhookError::InvalidRecording(reason) => write!(f, "invalid recording: {reason}"),
+            HudhookError::HookTargetNotFound(target) => write!(f, "couldn't find {target}"),
+            HudhookError::NoBackendDetected => write!(f, "no supported graphics API is loaded"),
+            HudhookError::ProcessNotFound(target) => write!(f, "couldn't find {target}"),
+            HudhookError::AmbiguousProcess {
+                target,
+                pids,
+            } => {
+                write!(f, "{target} matches several processes: {pids:?}")
+            }
+            HudhookError::RemoteLoadFailed(path) => {
+                write!(
+                    f,
+                    "LoadLibraryW failed in the target process for {}",
+                    path.display()
+                )
+            }
+            HudhookError::DrainTimeout(in_flight) => {
+                write!(
+                    f,
+                    "{in_flight} hooked calls were still running after the timeout"
+                )
+            }
+            HudhookError::AlreadyApplied => {
+                write!(f, "another hudhook instance is already applied")
+            }
+            HudhookError::BackendInUse(backend) => {
+                write!(f, "{backend:?} is already hooked by another instance")
+            }
+            HudhookError::BitnessMismatch => {
+                write!(f, "the target process and the injector differ in bitness")
+            }
+            HudhookError::AmbiguousBackend => {
+                write!(
+                    f,
+                    "both DirectX 11 and DirectX 12 are loaded and neither presented"
+                )
+            }
+            HudhookError::WndProcSubclassed(windows) => {
+                write!(
+                    f,
+                    "{windows} windows were subclassed again and still call into the overlay"
+                )
+            }
+            HudhookError::ModuleNotLoaded(module) => write!(f, "module {module} is not loaded"),
+            HudhookError::ExportNotFound {
+                module,
+                export,
+            } => {
+                write!(f, "module {module} has no export {export}")
+            }
+            HudhookError::InvalidPattern(reason) => write!(f, "invalid pattern: {reason}"),
+            HudhookError::PatternNotFound {
+                module,
+                pattern,
+            } => {
+                write!(f, "pattern {pattern} not found in {module}")
+            }
+            HudhookError::AmbiguousPattern {
+                pattern,
+                matches,
+            } => {
+                write!(f, "pattern {pattern} matches {matches} locations")
+            }
+        }
+    }
+}
+
+impl error::Error for HudhookError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            HudhookError::Windows(e) => Some(e),
+            HudhookError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<MH_STATUS> for HudhookError {
+    fn from(status: MH_STATUS) -> Self {
+        HudhookError::MinHook(status)
+    }
+}
+
+impl From<windows::core::Error> for HudhookError {
+    fn from(e: windows::core::Error) -> Self {
+        HudhookError::Windows(e)
+    }
+}
+
+impl From<io::Error> for HudhookError {
+    fn from(e: io::Error) -> Self {
+        HudhookError::Io(e)
+    }
+}
@@ -0,0 +1,230 @@
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+use std::{
+    ffi::c_void,
+    mem,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+use std::{
+    slice,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+use minhook_raw::sys::{MH_ApplyQueued, MH_STATUS};
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+use parking_lot::Mutex;
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+use windows::{
+    core::HRESULT,
+    Win32::Graphics::{
+        Direct3D11::ID3D11Device,
+        Direct3D12::ID3D12CommandQueue,
+        Dxgi::IDXGISwapChain,
+    },
+};
+use windows::{
+    core::{w, PCWSTR},
+    Win32::{
+        System::LibraryLoader::{
+            FindResourceW,
+            GetModuleHandleW,
+            LoadResource,
+            LockResource,
+            SizeofResource,
+        },
+        UI::WindowsAndMessaging::RT_VERSION,
+    },
+};
+
+use crate::error::Result;
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+use crate::{error::HudhookError, mh::MhHook, util::InFlight};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+pub(crate) const PRESENT_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const VS_VERSION_INFO: u16 = 1;
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+const PROBE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+static PRESENTER: Mutex<Option<Backend>> = parking_lot::const_mutex(None);
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+static PROBE_TRAMPOLINE: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+static PROBE_IN_FLIGHT: InFlight = InFlight::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Dx9,
+    Dx11,
+    Dx12,
+    OpenGl3,
+}
+
+impl Backend {
+    pub fn is_enabled(self) -> bool {
+        match self {
+            Backend::Dx9 => cfg!(feature = "dx9"),
+            Backend::Dx11 => cfg!(feature = "dx11"),
+            Backend::Dx12 => cfg!(feature = "dx12"),
+            Backend::OpenGl3 => cfg!(feature = "opengl3"),
+        }
+    }
+}
+
+fn is_loaded(module: PCWSTR) -> bool {
+    unsafe { GetModuleHandleW(module) }.is_ok()
+}
+
+// Under Wine vulkan-1.dll and opengl32.dll can be loaded whatever the game renders with, so DXVK is
+// recognized by the version resource of its own d3d11.dll or dxgi.dll instead.
+pub fn is_dxvk() -> bool {
+    [w!("d3d11.dll"), w!("dxgi.dll")]
+        .into_iter()
+        .any(|module| version_info_contains(module, "DXVK"))
+}
+
+fn version_info_contains(module: PCWSTR, text: &str) -> bool {
+    let Ok(module) = (unsafe { GetModuleHandleW(module) }) else {
+        return false;
+    };
+
+    let version_info = unsafe {
+        let resource = FindResourceW(module, PCWSTR(VS_VERSION_INFO as _), RT_VERSION);
+        if resource.is_invalid() {
+            return false;
+        }
+        let Ok(data) = LoadResource(module, resource) else {
+            return false;
+        };
+        let data = LockResource(data) as *const u8;
+        if data.is_null() {
+            return false;
+        }
+        slice::from_raw_parts(data, SizeofResource(module, resource) as usize)
+    };
+
+    // Version resource strings are UTF-16.
+    let text = text
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    version_info
+        .windows(text.len())
+        .any(|window| window == text)
+}
+
+pub fn detect() -> Vec<Backend> {
+    let dxvk = is_dxvk();
+
+    [
+        (Backend::Dx12, is_loaded(w!("d3d12.dll"))),
+        (Backend::Dx11, is_loaded(w!("d3d11.dll"))),
+        (Backend::Dx9, is_loaded(w!("d3d9.dll"))),
+        (Backend::OpenGl3, !dxvk && is_loaded(w!("opengl32.dll"))),
+    ]
+    .into_iter()
+    .filter(|&(backend, loaded)| loaded && backend.is_enabled())
+    .map(|(backend, _)| backend)
+    .collect()
+}
+
+// DX11 and DX12 both present through IDXGISwapChain and games often load both DLLs, so when both
+// are candidates only the one whose swap chain actually presents is kept. Guessing would hook the
+// wrong one, so nothing presenting in time is an error.
+#[cfg_attr(not(all(feature = "dx11", feature = "dx12")), allow(unused_variables))]
+pub fn retain_presenting(backends: Vec<Backend>, timeout: Duration) -> Result<Vec<Backend>> {
+    #[cfg(all(feature = "dx11", feature = "dx12"))]
+    if backends.contains(&Backend::Dx11) && backends.contains(&Backend::Dx12) {
+        let other = match probe_dxgi_presenter(timeout)? {
+            Some(Backend::Dx11) => Backend::Dx12,
+            Some(_) => Backend::Dx11,
+            None => return Err(HudhookError::AmbiguousBackend),
+        };
+        return Ok(backends.into_iter().filter(|&b| b != other).collect());
+    }
+
+    Ok(backends)
+}
+
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+fn probe_dxgi_presenter(timeout: Duration) -> Result<Option<Backend>> {
+    let present = super::dx11::get_target_addrs()?;
+
+    *PRESENTER.lock() = None;
+    let hook = unsafe { MhHook::new(present as *mut c_void, probe_present_impl as *mut c_void)? };
+    PROBE_TRAMPOLINE.store(hook.trampoline(), Ordering::SeqCst);
+
+    let presenter = unsafe { hook.queue_enable().and_then(|_| apply_queued()) }.map(|_| {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(presenter) = *PRESENTER.lock() {
+                return Some(presenter);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            thread::sleep(PROBE_POLL_INTERVAL);
+        }
+    });
+
+    let disabled = unsafe { hook.queue_disable().and_then(|_| apply_queued()) };
+    let deadline = Instant::now() + timeout;
+    while PROBE_IN_FLIGHT.count() > 0 {
+        // Removing the hook would free the trampoline under that call, so it is left disabled.
+        if Instant::now() >= deadline {
+            return Err(HudhookError::DrainTimeout(PROBE_IN_FLIGHT.count()));
+        }
+        thread::sleep(PROBE_POLL_INTERVAL);
+    }
+    unsafe { hook.remove()? };
+
+    disabled.and(presenter)
+}
+
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+unsafe fn apply_queued() -> Result<()> {
+    match MH_ApplyQueued() {
+        MH_STATUS::MH_OK => Ok(()),
+        status => Err(HudhookError::MinHook(status)),
+    }
+}
+
+#[cfg(all(feature = "dx11", feature = "dx12"))]
+unsafe extern "system" fn probe_present_impl(
+    swap_chain: IDXGISwapChain,
+    sync_interval: u32,
+    flags: u32,
+) -> HRESULT {
+    let _in_flight = PROBE_IN_FLIGHT.enter();
+
+    // A DX12 swap chain is created on a command queue rather than on a device.
+    let presenter = if swap_chain.GetDevice::<ID3D12CommandQueue>().is_ok() {
+        Some(Backend::Dx12)
+    } else if swap_chain.GetDevice::<ID3D11Device>().is_ok() {
+        Some(Backend::Dx11)
+    } else {
+        None
+    };
+    if presenter.is_some() {
+        *PRESENTER.lock() = presenter;
+    }
+
+    let trampoline = mem::transmute::<*mut c_void, super::dx11::DXGISwapChainPresentType>(
+        PROBE_TRAMPOLINE.load(Ordering::SeqCst),
+    );
+    trampoline(swap_chain, sync_interval, flags)
+}
+
+pub fn wait_for(timeout: Duration) -> Vec<Backend> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let backends = detect();
+        if !backends.is_empty() || Instant::now() >= deadline {
+            return backends;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -1,12 +1,17 @@
-use std::{ffi::c_void, mem, sync::OnceLock};
+use std::{
+    ffi::c_void,
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use imgui::Context;
-use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use windows::{
-    core::{Error, Interface, Result, HRESULT},
+    core::{Interface, HRESULT},
     Win32::{
-        Foundation::BOOL,
+        Foundation::{BOOL, HWND},
         Graphics::{
             Direct3D::{D3D_DRIVER_TYPE_NULL, D3D_FEATURE_LEVEL_10_0, D3D_FEATURE_LEVEL_11_0},
             Direct3D11::{
@@ -34,59 +39,56 @@ use windows::{
     },
 };
 
-use super::DummyHwnd;
+use super::{DummyHwnd, InstanceSlot};
 use crate::{
+    error::{HudhookError, Result},
     mh::MhHook,
-    renderer::{D3D11RenderEngine, Pipeline},
-    util,
+    renderer::{D3D11RenderEngine, PipelineMap, RenderLoopSource, RENDER_LOCK_TIMEOUT},
+    util::{self, InFlight},
+    Backend,
     Hooks,
     ImguiRenderLoop,
 };
 
-type DXGISwapChainPresentType =
+pub(super) type DXGISwapChainPresentType =
     unsafe extern "system" fn(This: IDXGISwapChain, SyncInterval: u32, Flags: u32) -> HRESULT;
 
 struct Trampolines {
     dxgi_swap_chain_present: DXGISwapChainPresentType,
 }
 
-static mut TRAMPOLINES: OnceLock<Trampolines> = OnceLock::new();
-static mut PIPELINE: OnceCell<Mutex<Pipeline<D3D11RenderEngine>>> = OnceCell::new();
-static mut RENDER_LOOP: OnceCell<Box<dyn ImguiRenderLoop + Send + Sync>> = OnceCell::new();
-
-unsafe fn init_pipeline(swap_chain: &IDXGISwapChain) -> Result<Mutex<Pipeline<D3D11RenderEngine>>> {
-    let hwnd = util::try_out_param(|v| swap_chain.GetDesc(v)).map(|desc| desc.OutputWindow)?;
-
-    let mut ctx = Context::create();
-    let engine = D3D11RenderEngine::new(&swap_chain.GetDevice()?, &mut ctx)?;
+struct State {
+    trampolines: Trampolines,
+    pipelines: Mutex<PipelineMap<D3D11RenderEngine>>,
+    in_flight: InFlight,
+    enabled: Arc<AtomicBool>,
+}
 
-    let Some(render_loop) = RENDER_LOOP.take() else {
-        return Err(Error::from_hresult(HRESULT(-1)));
-    };
+unsafe impl Send for State {}
+unsafe impl Sync for State {}
 
-    let pipeline = Pipeline::new(hwnd, ctx, engine, render_loop).map_err(|(e, render_loop)| {
-        RENDER_LOOP.get_or_init(move || render_loop);
-        e
-    })?;
+static STATE: InstanceSlot<State> = InstanceSlot::new();
 
-    Ok(Mutex::new(pipeline))
-}
-
-fn render(swap_chain: &IDXGISwapChain) -> Result<()> {
+fn render(state: &State, swap_chain: &IDXGISwapChain) -> Result<()> {
     unsafe {
-        let pipeline = PIPELINE.get_or_try_init(|| init_pipeline(swap_chain))?;
-
-        let Some(mut pipeline) = pipeline.try_lock() else {
-            return Err(Error::from_hresult(HRESULT(-1)));
+        let Some(mut pipelines) = state.pipelines.try_lock_for(RENDER_LOCK_TIMEOUT) else {
+            return Err(HudhookError::LockContention);
         };
 
-        pipeline.prepare_render()?;
+        let hwnd = util::try_out_param(|v| swap_chain.GetDesc(v)).map(|desc| desc.OutputWindow)?;
+
+        pipelines.render(
+            hwnd,
+            |ctx| D3D11RenderEngine::new(&swap_chain.GetDevice()?, ctx),
+            |pipeline| {
+                pipeline.prepare_render()?;
 
-        let target: ID3D11Texture2D = swap_chain.GetBuffer(0)?;
+                let target: ID3D11Texture2D = swap_chain.GetBuffer(0)?;
 
-        pipeline.render(target)?;
+                pipeline.render(target)
+            },
+        )
     }
-    Ok(())
 }
 
 unsafe extern "system" fn dxgi_swap_chain_present_impl(
@@ -94,18 +96,15 @@ unsafe extern "system" fn dxgi_swap_chain_present_impl(
     sync_interval: u32,
     flags: u32,
 ) -> HRESULT {
-    let Trampolines {
-        dxgi_swap_chain_present,
-    } = TRAMPOLINES
-        .get()
-        .expect("DirectX 11 trampolines uninitialized");
+    let state = STATE.get().expect("DirectX 11 hooks uninitialized");
+    let _in_flight = state.in_flight.enter();
 
-    let _ = render(&swap_chain);
+    let _ = render(&state, &swap_chain);
 
-    dxgi_swap_chain_present(swap_chain, sync_interval, flags)
+    (state.trampolines.dxgi_swap_chain_present)(swap_chain, sync_interval, flags)
 }
 
-fn get_target_addrs() -> DXGISwapChainPresentType {
+pub(super) fn get_target_addrs() -> Result<DXGISwapChainPresentType> {
     let mut p_device: Option<ID3D11Device> = None;
     let mut p_context: Option<ID3D11DeviceContext> = None;
     let mut p_swap_chain: Option<IDXGISwapChain> = None;
@@ -141,11 +140,12 @@ fn get_target_addrs() -> DXGISwapChainPresentType {
             Some(&mut p_device),
             None,
             Some(&mut p_context),
-        )
-        .expect("D3D11CreateDeviceAndSwapChain failed");
+        )?;
     }
 
-    let swap_chain = p_swap_chain.unwrap();
+    let Some(swap_chain) = p_swap_chain else {
+        return Err(HudhookError::HookTargetNotFound("IDXGISwapChain"));
+    };
 
     let present_ptr: DXGISwapChainPresentType = unsafe {
         mem::transmute::<
@@ -154,34 +154,77 @@ fn get_target_addrs() -> DXGISwapChainPresentType {
         >(swap_chain.vtable().Present)
     };
 
-    present_ptr
+    Ok(present_ptr)
 }
 
-pub struct ImguiDx11Hooks([MhHook; 1]);
+pub struct ImguiDx11Hooks {
+    hooks: [MhHook; 1],
+    state: Arc<State>,
+}
 
 impl ImguiDx11Hooks {
     pub unsafe fn new<T>(t: T) -> Self
     where
         T: ImguiRenderLoop + Send + Sync + 'static,
     {
-        let dxgi_swap_chain_present_addr = get_target_addrs();
+        match Self::try_new(t) {
+            Ok(hooks) => hooks,
+            Err((e, _)) => panic!("couldn't create DirectX 11 hooks: {e}"),
+        }
+    }
+
+    pub unsafe fn try_new<T>(t: T) -> Result<Self, (HudhookError, T)>
+    where
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        match Self::create_hooks() {
+            Ok(hooks) => Ok(Self::install(hooks, RenderLoopSource::single(Box::new(t)))),
+            Err(e) => Err((e, t)),
+        }
+    }
+
+    pub unsafe fn with_factory<F>(factory: F) -> Self
+    where
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        match Self::create_hooks() {
+            Ok(hooks) => Self::install(hooks, RenderLoopSource::factory(factory)),
+            Err(e) => panic!("couldn't create DirectX 11 hooks: {e}"),
+        }
+    }
 
-        let hook_present = MhHook::new(
+    unsafe fn create_hooks() -> Result<[MhHook; 1]> {
+        // Detours find their state through STATE, so a backend can only be hooked once.
+        if STATE.is_occupied() {
+            return Err(HudhookError::BackendInUse(Backend::Dx11));
+        }
+
+        let dxgi_swap_chain_present_addr = get_target_addrs()?;
+
+        MhHook::new_all([(
             dxgi_swap_chain_present_addr as *mut _,
             dxgi_swap_chain_present_impl as *mut _,
-        )
-        .expect("couldn't create IDXGISwapChain::Present hook");
+        )])
+    }
 
-        RENDER_LOOP.get_or_init(|| Box::new(t));
-        TRAMPOLINES.get_or_init(|| {
-            Trampolines {
+    unsafe fn install([hook_present]: [MhHook; 1], source: RenderLoopSource) -> Self {
+        let enabled = Arc::new(AtomicBool::new(true));
+        let state = Arc::new(State {
+            trampolines: Trampolines {
                 dxgi_swap_chain_present: mem::transmute::<*mut c_void, DXGISwapChainPresentType>(
                     hook_present.trampoline(),
                 ),
-            }
+            },
+            pipelines: Mutex::new(PipelineMap::new(source, Arc::clone(&enabled))),
+            in_flight: InFlight::new(),
+            enabled,
         });
+        STATE.set(&state);
 
-        Self([hook_present])
+        Self {
+            hooks: [hook_present],
+            state,
+        }
     }
 }
 
@@ -194,13 +237,44 @@ impl Hooks for ImguiDx11Hooks {
         Box::new(unsafe { Self::new(t) })
     }
 
+    fn try_from_render_loop<T>(t: T) -> Result<Box<Self>, (HudhookError, T)>
+    where
+        Self: Sized,
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        unsafe { Self::try_new(t) }.map(Box::new)
+    }
+
+    fn from_render_loop_factory<F>(factory: F) -> Box<Self>
+    where
+        Self: Sized,
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        Box::new(unsafe { Self::with_factory(factory) })
+    }
+
     fn hooks(&self) -> &[MhHook] {
-        &self.0
+        &self.hooks
+    }
+
+    fn in_flight(&self) -> usize {
+        self.state.in_flight.count()
+    }
+
+    fn backend(&self) -> Option<Backend> {
+        Some(Backend::Dx11)
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.state.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    fn restore_wnd_procs(&self) -> usize {
+        self.state.pipelines.lock().restore_wnd_procs()
     }
 
     unsafe fn unhook(&mut self) {
-        TRAMPOLINES.take();
-        PIPELINE.take().map(|p| p.into_inner().take());
-        RENDER_LOOP.take();
+        STATE.clear(&self.state);
+        self.state.pipelines.lock().clear();
     }
 }
@@ -1,12 +1,17 @@
-use std::{ffi::c_void, mem, sync::OnceLock};
+use std::{
+    ffi::c_void,
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use imgui::Context;
-use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use windows::{
-    core::{Error, Interface, Result, HRESULT},
+    core::{Interface, HRESULT},
     Win32::{
-        Foundation::BOOL,
+        Foundation::{BOOL, HWND},
         Graphics::{
             Direct3D::D3D_FEATURE_LEVEL_11_0,
             Direct3D12::{
@@ -42,11 +47,13 @@ use windows::{
     },
 };
 
-use super::DummyHwnd;
+use super::{DummyHwnd, InstanceSlot};
 use crate::{
+    error::{HudhookError, Result},
     mh::MhHook,
-    renderer::{D3D12RenderEngine, Pipeline},
-    util,
+    renderer::{D3D12RenderEngine, PipelineMap, RenderLoopSource, RENDER_LOCK_TIMEOUT},
+    util::{self, InFlight},
+    Backend,
     Hooks,
     ImguiRenderLoop,
 };
@@ -75,53 +82,59 @@ struct Trampolines {
     d3d12_command_queue_execute_command_lists: D3D12CommandQueueExecuteCommandListsType,
 }
 
-static mut TRAMPOLINES: OnceLock<Trampolines> = OnceLock::new();
-
-static mut PIPELINE: OnceCell<Mutex<Pipeline<D3D12RenderEngine>>> = OnceCell::new();
-static mut COMMAND_QUEUE: OnceCell<ID3D12CommandQueue> = OnceCell::new();
-static mut RENDER_LOOP: OnceCell<Box<dyn ImguiRenderLoop + Send + Sync>> = OnceCell::new();
-
-unsafe fn init_pipeline(
-    swap_chain: &IDXGISwapChain3,
-) -> Result<Mutex<Pipeline<D3D12RenderEngine>>> {
-    let Some(command_queue) = COMMAND_QUEUE.get() else {
-        return Err(Error::from_hresult(HRESULT(-1)));
-    };
+struct State {
+    trampolines: Trampolines,
+    pipelines: Mutex<PipelineMap<D3D12RenderEngine>>,
+    in_flight: InFlight,
+    enabled: Arc<AtomicBool>,
+    command_queues: Mutex<Vec<(ID3D12Device, ID3D12CommandQueue)>>,
+}
 
-    let hwnd = util::try_out_param(|v| swap_chain.GetDesc(v)).map(|desc| desc.OutputWindow)?;
+unsafe impl Send for State {}
+unsafe impl Sync for State {}
 
-    let mut ctx = Context::create();
-    let engine = D3D12RenderEngine::new(command_queue, &mut ctx)?;
+static STATE: InstanceSlot<State> = InstanceSlot::new();
 
-    let Some(render_loop) = RENDER_LOOP.take() else {
-        return Err(Error::from_hresult(HRESULT(-1)));
-    };
+// DXGI hands back the queue a swap chain was created on. Failing that, a direct queue seen
+// executing on the device that owns its back buffers is used.
+unsafe fn command_queue(state: &State, swap_chain: &IDXGISwapChain3) -> Result<ID3D12CommandQueue> {
+    if let Ok(command_queue) = swap_chain.GetDevice::<ID3D12CommandQueue>() {
+        return Ok(command_queue);
+    }
 
-    let pipeline = Pipeline::new(hwnd, ctx, engine, render_loop).map_err(|(e, render_loop)| {
-        RENDER_LOOP.get_or_init(move || render_loop);
-        e
-    })?;
+    let buffer: ID3D12Resource = swap_chain.GetBuffer(0)?;
+    let device: ID3D12Device = util::try_out_ptr(|v| buffer.GetDevice(v))?;
 
-    Ok(Mutex::new(pipeline))
+    state
+        .command_queues
+        .lock()
+        .iter()
+        .find(|(queue_device, _)| *queue_device == device)
+        .map(|(_, command_queue)| command_queue.clone())
+        .ok_or(HudhookError::PipelineNotReady)
 }
 
-fn render(swap_chain: &IDXGISwapChain3) -> Result<()> {
+fn render(state: &State, swap_chain: &IDXGISwapChain3) -> Result<()> {
     unsafe {
-        let pipeline = PIPELINE.get_or_try_init(|| init_pipeline(swap_chain))?;
-
-        let Some(mut pipeline) = pipeline.try_lock() else {
-            return Err(Error::from_hresult(HRESULT(-1)));
+        let Some(mut pipelines) = state.pipelines.try_lock_for(RENDER_LOCK_TIMEOUT) else {
+            return Err(HudhookError::LockContention);
         };
 
-        pipeline.prepare_render()?;
+        let hwnd = util::try_out_param(|v| swap_chain.GetDesc(v)).map(|desc| desc.OutputWindow)?;
 
-        let target: ID3D12Resource =
-            swap_chain.GetBuffer(swap_chain.GetCurrentBackBufferIndex())?;
+        pipelines.render(
+            hwnd,
+            |ctx| D3D12RenderEngine::new(&command_queue(state, swap_chain)?, ctx),
+            |pipeline| {
+                pipeline.prepare_render()?;
 
-        pipeline.render(target)?;
-    }
+                let target: ID3D12Resource =
+                    swap_chain.GetBuffer(swap_chain.GetCurrentBackBufferIndex())?;
 
-    Ok(())
+                pipeline.render(target)
+            },
+        )
+    }
 }
 
 unsafe extern "system" fn dxgi_swap_chain_present_impl(
@@ -129,15 +142,11 @@ unsafe extern "system" fn dxgi_swap_chain_present_impl(
     sync_interval: u32,
     flags: u32,
 ) -> HRESULT {
-    let Trampolines {
-        dxgi_swap_chain_present,
-        ..
-    } = TRAMPOLINES
-        .get()
-        .expect("DirectX 12 trampolines uninitialized");
-
-    let _ = render(&swap_chain);
-    dxgi_swap_chain_present(swap_chain, sync_interval, flags)
+    let state = STATE.get().expect("DirectX 12 hooks uninitialized");
+    let _in_flight = state.in_flight.enter();
+
+    let _ = render(&state, &swap_chain);
+    (state.trampolines.dxgi_swap_chain_present)(swap_chain, sync_interval, flags)
 }
 
 unsafe extern "system" fn dxgi_swap_chain_resize_buffers_impl(
@@ -148,14 +157,17 @@ unsafe extern "system" fn dxgi_swap_chain_resize_buffers_impl(
     new_format: DXGI_FORMAT,
     flags: u32,
 ) -> HRESULT {
-    let Trampolines {
-        dxgi_swap_chain_resize_buffers,
-        ..
-    } = TRAMPOLINES
-        .get()
-        .expect("DirectX 12 trampolines uninitialized");
-
-    dxgi_swap_chain_resize_buffers(p_this, buffer_count, width, height, new_format, flags)
+    let state = STATE.get().expect("DirectX 12 hooks uninitialized");
+    let _in_flight = state.in_flight.enter();
+
+    (state.trampolines.dxgi_swap_chain_resize_buffers)(
+        p_this,
+        buffer_count,
+        width,
+        height,
+        new_format,
+        flags,
+    )
 }
 
 unsafe extern "system" fn d3d12_command_queue_execute_command_lists_impl(
@@ -163,40 +175,37 @@ unsafe extern "system" fn d3d12_command_queue_execute_command_lists_impl(
     num_command_lists: u32,
     command_lists: *mut ID3D12CommandList,
 ) {
-    let Trampolines {
-        d3d12_command_queue_execute_command_lists,
-        ..
-    } = TRAMPOLINES
-        .get()
-        .expect("DirectX 12 trampolines uninitialized");
-
-    COMMAND_QUEUE
-        .get_or_try_init(|| unsafe {
-            let desc = command_queue.GetDesc();
-            if desc.Type == D3D12_COMMAND_LIST_TYPE_DIRECT {
-                Ok(command_queue.clone())
-            } else {
-                Err(())
+    let state = STATE.get().expect("DirectX 12 hooks uninitialized");
+    let _in_flight = state.in_flight.enter();
+
+    if command_queue.GetDesc().Type == D3D12_COMMAND_LIST_TYPE_DIRECT {
+        if let Ok(device) = util::try_out_ptr(|v| command_queue.GetDevice::<ID3D12Device>(v)) {
+            let mut command_queues = state.command_queues.lock();
+            if !command_queues.iter().any(|(d, _)| *d == device) {
+                command_queues.push((device, command_queue.clone()));
             }
-        })
-        .ok();
+        }
+    }
 
-    d3d12_command_queue_execute_command_lists(command_queue, num_command_lists, command_lists);
+    (state.trampolines.d3d12_command_queue_execute_command_lists)(
+        command_queue,
+        num_command_lists,
+        command_lists,
+    );
 }
 
-fn get_target_addrs() -> (
+fn get_target_addrs() -> Result<(
     DXGISwapChainPresentType,
     DXGISwapChainResizeBuffersType,
     D3D12CommandQueueExecuteCommandListsType,
-) {
+)> {
     let dummy_hwnd = DummyHwnd::new();
 
-    let factory: IDXGIFactory2 = unsafe { CreateDXGIFactory2(0) }.unwrap();
-    let adapter = unsafe { factory.EnumAdapters(0) }.unwrap();
+    let factory: IDXGIFactory2 = unsafe { CreateDXGIFactory2(0) }?;
+    let adapter = unsafe { factory.EnumAdapters(0) }?;
 
     let device: ID3D12Device =
-        util::try_out_ptr(|v| unsafe { D3D12CreateDevice(&adapter, D3D_FEATURE_LEVEL_11_0, v) })
-            .expect("D3D12CreateDevice failed");
+        util::try_out_ptr(|v| unsafe { D3D12CreateDevice(&adapter, D3D_FEATURE_LEVEL_11_0, v) })?;
 
     let command_queue: ID3D12CommandQueue = unsafe {
         device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
@@ -205,10 +214,9 @@ fn get_target_addrs() -> (
             Flags: D3D12_COMMAND_QUEUE_FLAG_NONE,
             NodeMask: 0,
         })
-    }
-    .unwrap();
+    }?;
 
-    let swap_chain: IDXGISwapChain = match util::try_out_ptr(|v| unsafe {
+    let swap_chain: IDXGISwapChain = util::try_out_ptr(|v| unsafe {
         factory
             .CreateSwapChain(
                 &command_queue,
@@ -238,12 +246,7 @@ fn get_target_addrs() -> (
                 v,
             )
             .ok()
-    }) {
-        Ok(swap_chain) => swap_chain,
-        Err(e) => {
-            panic!("{e:?}");
-        }
-    };
+    })?;
 
     let present_ptr: DXGISwapChainPresentType =
         unsafe { mem::transmute(swap_chain.vtable().Present) };
@@ -252,42 +255,80 @@ fn get_target_addrs() -> (
     let cqecl_ptr: D3D12CommandQueueExecuteCommandListsType =
         unsafe { mem::transmute(command_queue.vtable().ExecuteCommandLists) };
 
-    (present_ptr, resize_buffers_ptr, cqecl_ptr)
+    Ok((present_ptr, resize_buffers_ptr, cqecl_ptr))
 }
 
-pub struct ImguiDx12Hooks([MhHook; 3]);
+pub struct ImguiDx12Hooks {
+    hooks: [MhHook; 3],
+    state: Arc<State>,
+}
 
 impl ImguiDx12Hooks {
     pub unsafe fn new<T>(t: T) -> Self
     where
         T: ImguiRenderLoop + Send + Sync + 'static,
     {
+        match Self::try_new(t) {
+            Ok(hooks) => hooks,
+            Err((e, _)) => panic!("couldn't create DirectX 12 hooks: {e}"),
+        }
+    }
+
+    pub unsafe fn try_new<T>(t: T) -> Result<Self, (HudhookError, T)>
+    where
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        match Self::create_hooks() {
+            Ok(hooks) => Ok(Self::install(hooks, RenderLoopSource::single(Box::new(t)))),
+            Err(e) => Err((e, t)),
+        }
+    }
+
+    pub unsafe fn with_factory<F>(factory: F) -> Self
+    where
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        match Self::create_hooks() {
+            Ok(hooks) => Self::install(hooks, RenderLoopSource::factory(factory)),
+            Err(e) => panic!("couldn't create DirectX 12 hooks: {e}"),
+        }
+    }
+
+    unsafe fn create_hooks() -> Result<[MhHook; 3]> {
+        // Detours find their state through STATE, so a backend can only be hooked once.
+        if STATE.is_occupied() {
+            return Err(HudhookError::BackendInUse(Backend::Dx12));
+        }
+
         let (
             dxgi_swap_chain_present_addr,
             dxgi_swap_chain_resize_buffers_addr,
             d3d12_command_queue_execute_command_lists_addr,
-        ) = get_target_addrs();
-
-        let hook_present = MhHook::new(
-            dxgi_swap_chain_present_addr as *mut _,
-            dxgi_swap_chain_present_impl as *mut _,
-        )
-        .expect("couldn't create IDXGISwapChain::Present hook");
-        let hook_resize_buffers = MhHook::new(
-            dxgi_swap_chain_resize_buffers_addr as *mut _,
-            dxgi_swap_chain_resize_buffers_impl as *mut _,
-        )
-        .expect("couldn't create IDXGISwapChain::ResizeBuffers hook");
-        let hook_cqecl = MhHook::new(
-            d3d12_command_queue_execute_command_lists_addr as *mut _,
-            d3d12_command_queue_execute_command_lists_impl as *mut _,
-        )
-        .expect("couldn't create ID3D12CommandQueue::ExecuteCommandLists hook");
-
-        RENDER_LOOP.get_or_init(|| Box::new(t));
+        ) = get_target_addrs()?;
+
+        MhHook::new_all([
+            (
+                dxgi_swap_chain_present_addr as *mut _,
+                dxgi_swap_chain_present_impl as *mut _,
+            ),
+            (
+                dxgi_swap_chain_resize_buffers_addr as *mut _,
+                dxgi_swap_chain_resize_buffers_impl as *mut _,
+            ),
+            (
+                d3d12_command_queue_execute_command_lists_addr as *mut _,
+                d3d12_command_queue_execute_command_lists_impl as *mut _,
+            ),
+        ])
+    }
 
-        TRAMPOLINES.get_or_init(|| {
-            Trampolines {
+    unsafe fn install(
+        [hook_present, hook_resize_buffers, hook_cqecl]: [MhHook; 3],
+        source: RenderLoopSource,
+    ) -> Self {
+        let enabled = Arc::new(AtomicBool::new(true));
+        let state = Arc::new(State {
+            trampolines: Trampolines {
                 dxgi_swap_chain_present: mem::transmute::<*mut c_void, DXGISwapChainPresentType>(
                     hook_present.trampoline(),
                 ),
@@ -301,10 +342,18 @@ impl ImguiDx12Hooks {
                 >(
                     hook_cqecl.trampoline()
                 ),
-            }
+            },
+            pipelines: Mutex::new(PipelineMap::new(source, Arc::clone(&enabled))),
+            in_flight: InFlight::new(),
+            enabled,
+            command_queues: Mutex::new(Vec::new()),
         });
+        STATE.set(&state);
 
-        Self([hook_present, hook_resize_buffers, hook_cqecl])
+        Self {
+            hooks: [hook_present, hook_resize_buffers, hook_cqecl],
+            state,
+        }
     }
 }
 
@@ -317,14 +366,44 @@ impl Hooks for ImguiDx12Hooks {
         Box::new(unsafe { Self::new(t) })
     }
 
+    fn try_from_render_loop<T>(t: T) -> Result<Box<Self>, (HudhookError, T)>
+    where
+        Self: Sized,
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        unsafe { Self::try_new(t) }.map(Box::new)
+    }
+
+    fn from_render_loop_factory<F>(factory: F) -> Box<Self>
+    where
+        Self: Sized,
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        Box::new(unsafe { Self::with_factory(factory) })
+    }
+
     fn hooks(&self) -> &[MhHook] {
-        &self.0
+        &self.hooks
+    }
+
+    fn in_flight(&self) -> usize {
+        self.state.in_flight.count()
+    }
+
+    fn backend(&self) -> Option<Backend> {
+        Some(Backend::Dx12)
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.state.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    fn restore_wnd_procs(&self) -> usize {
+        self.state.pipelines.lock().restore_wnd_procs()
     }
 
     unsafe fn unhook(&mut self) {
-        TRAMPOLINES.take();
-        PIPELINE.take().map(|p| p.into_inner().take());
-        COMMAND_QUEUE.take();
-        RENDER_LOOP.take();
+        STATE.clear(&self.state);
+        self.state.pipelines.lock().clear();
     }
 }
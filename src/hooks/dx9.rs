@@ -1,10 +1,15 @@
-use std::{ffi::c_void, mem, sync::OnceLock};
+use std::{
+    ffi::c_void,
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use imgui::Context;
-use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use windows::{
-    core::{Error, Interface, Result, HRESULT},
+    core::{Interface, HRESULT},
     Win32::{
         Foundation::{BOOL, HWND, RECT},
         Graphics::{
@@ -26,11 +31,13 @@ use windows::{
     },
 };
 
-use super::DummyHwnd;
+use super::{DummyHwnd, InstanceSlot};
 use crate::{
+    error::{HudhookError, Result},
     mh::MhHook,
-    renderer::{D3D9RenderEngine, Pipeline},
-    util,
+    renderer::{D3D9RenderEngine, PipelineMap, RenderLoopSource, RENDER_LOCK_TIMEOUT},
+    util::{self, InFlight},
+    Backend,
     Hooks,
     ImguiRenderLoop,
 };
@@ -51,47 +58,46 @@ struct Trampolines {
     dx9_reset: Dx9ResetType,
 }
 
-static mut TRAMPOLINES: OnceLock<Trampolines> = OnceLock::new();
-static mut PIPELINE: OnceCell<Mutex<Pipeline<D3D9RenderEngine>>> = OnceCell::new();
-static mut RENDER_LOOP: OnceCell<Box<dyn ImguiRenderLoop + Send + Sync>> = OnceCell::new();
-
-unsafe fn init_pipeline(device: &IDirect3DDevice9) -> Result<Mutex<Pipeline<D3D9RenderEngine>>> {
-    let mut creation_parameters = Default::default();
-    device.GetCreationParameters(&mut creation_parameters)?;
-
-    let hwnd = creation_parameters.hFocusWindow;
+struct State {
+    trampolines: Trampolines,
+    pipelines: Mutex<PipelineMap<D3D9RenderEngine>>,
+    in_flight: InFlight,
+    enabled: Arc<AtomicBool>,
+}
 
-    let mut ctx = Context::create();
+unsafe impl Send for State {}
+unsafe impl Sync for State {}
 
-    let engine = D3D9RenderEngine::new(device, &mut ctx)?;
+static STATE: InstanceSlot<State> = InstanceSlot::new();
 
-    let Some(render_loop) = RENDER_LOOP.take() else {
-        return Err(Error::from_hresult(HRESULT(-1)));
+fn render(state: &State, device: &IDirect3DDevice9, hwnd_override: HWND) -> Result<()> {
+    let Some(mut pipelines) = state.pipelines.try_lock_for(RENDER_LOCK_TIMEOUT) else {
+        return Err(HudhookError::LockContention);
     };
 
-    let pipeline = Pipeline::new(hwnd, ctx, engine, render_loop).map_err(|(e, render_loop)| {
-        RENDER_LOOP.get_or_init(move || render_loop);
-        e
-    })?;
-    Ok(Mutex::new(pipeline))
-}
-
-fn render(device: &IDirect3DDevice9) -> Result<()> {
-    let pipeline = unsafe { PIPELINE.get_or_try_init(|| init_pipeline(device)) }?;
-
-    let Some(mut pipeline) = pipeline.try_lock() else {
-        return Err(Error::from_hresult(HRESULT(-1)));
+    let hwnd = if hwnd_override.0 != 0 {
+        hwnd_override
+    } else {
+        let mut creation_parameters = Default::default();
+        unsafe { device.GetCreationParameters(&mut creation_parameters) }?;
+        creation_parameters.hFocusWindow
     };
 
-    pipeline.prepare_render()?;
+    pipelines.render(
+        hwnd,
+        |ctx| D3D9RenderEngine::new(device, ctx),
+        |pipeline| {
+            pipeline.prepare_render()?;
 
-    let surface = unsafe { device.GetBackBuffer(0, 0, D3DBACKBUFFER_TYPE_MONO)? };
+            let surface = unsafe { device.GetBackBuffer(0, 0, D3DBACKBUFFER_TYPE_MONO)? };
 
-    unsafe { device.BeginScene() }?;
-    pipeline.render(surface)?;
-    unsafe { device.EndScene() }?;
+            unsafe { device.BeginScene() }?;
+            pipeline.render(surface)?;
+            unsafe { device.EndScene() }?;
 
-    Ok(())
+            Ok(())
+        },
+    )
 }
 
 unsafe extern "system" fn dx9_present_impl(
@@ -101,15 +107,12 @@ unsafe extern "system" fn dx9_present_impl(
     hdestwindowoverride: HWND,
     pdirtyregion: *const RGNDATA,
 ) -> HRESULT {
-    let Trampolines {
-        dx9_present, ..
-    } = TRAMPOLINES
-        .get()
-        .expect("DirectX 9 trampolines uninitialized");
+    let state = STATE.get().expect("DirectX 9 hooks uninitialized");
+    let _in_flight = state.in_flight.enter();
 
-    let _ = render(&device);
+    let _ = render(&state, &device, hdestwindowoverride);
 
-    dx9_present(
+    (state.trampolines.dx9_present)(
         device,
         psourcerect,
         pdestrect,
@@ -121,26 +124,18 @@ unsafe extern "system" fn dx9_reset_impl(
     this: IDirect3DDevice9,
     present_params: *const D3DPRESENT_PARAMETERS,
 ) -> HRESULT {
-    let Trampolines {
-        dx9_reset, ..
-    } = TRAMPOLINES
-        .get()
-        .expect("DirectX 9 trampolines uninitialized");
-
-    if let Some(pipeline) = PIPELINE.take() {
-        let render_loop = pipeline.into_inner().take();
-
-        RENDER_LOOP
-            .set(render_loop)
-            .map_err(|_| ())
-            .expect("Render loop cell should be empty");
-    }
+    let state = STATE.get().expect("DirectX 9 hooks uninitialized");
+    let _in_flight = state.in_flight.enter();
+
+    state.pipelines.lock().reset();
 
-    dx9_reset(this, present_params)
+    (state.trampolines.dx9_reset)(this, present_params)
 }
 
-fn get_target_addrs() -> (Dx9PresentType, Dx9ResetType) {
-    let d9 = unsafe { Direct3DCreate9(D3D_SDK_VERSION).unwrap() };
+fn get_target_addrs() -> Result<(Dx9PresentType, Dx9ResetType)> {
+    let Some(d9) = (unsafe { Direct3DCreate9(D3D_SDK_VERSION) }) else {
+        return Err(HudhookError::HookTargetNotFound("IDirect3D9"));
+    };
 
     let mut d3d_display_mode = D3DDISPLAYMODE {
         Width: 0,
@@ -148,10 +143,7 @@ fn get_target_addrs() -> (Dx9PresentType, Dx9ResetType) {
         RefreshRate: 0,
         Format: D3DFORMAT(0),
     };
-    unsafe {
-        d9.GetAdapterDisplayMode(D3DADAPTER_DEFAULT, &mut d3d_display_mode)
-            .unwrap()
-    };
+    unsafe { d9.GetAdapterDisplayMode(D3DADAPTER_DEFAULT, &mut d3d_display_mode) }?;
 
     let mut present_params = D3DPRESENT_PARAMETERS {
         Windowed: BOOL(1),
@@ -170,14 +162,13 @@ fn get_target_addrs() -> (Dx9PresentType, Dx9ResetType) {
             &mut present_params,
             v,
         )
-    })
-    .expect("IDirect3DDevice9::CreateDevice: failed to create device");
+    })?;
 
     let present_ptr = device.vtable().Present;
     let reset_ptr = device.vtable().Reset;
 
     unsafe {
-        (
+        Ok((
             mem::transmute::<
                 unsafe extern "system" fn(
                     *mut c_void,
@@ -192,38 +183,82 @@ fn get_target_addrs() -> (Dx9PresentType, Dx9ResetType) {
                 unsafe extern "system" fn(*mut c_void, *mut D3DPRESENT_PARAMETERS) -> HRESULT,
                 Dx9ResetType,
             >(reset_ptr),
-        )
+        ))
     }
 }
 
-pub struct ImguiDx9Hooks([MhHook; 2]);
+pub struct ImguiDx9Hooks {
+    hooks: [MhHook; 2],
+    state: Arc<State>,
+}
 
 impl ImguiDx9Hooks {
     pub unsafe fn new<T>(t: T) -> Self
     where
         T: ImguiRenderLoop + Send + Sync + 'static,
     {
-        let (dx9_present_addr, dx9_reset_addr) = get_target_addrs();
+        match Self::try_new(t) {
+            Ok(hooks) => hooks,
+            Err((e, _)) => panic!("couldn't create DirectX 9 hooks: {e}"),
+        }
+    }
 
-        let hook_present = MhHook::new(
-            dx9_present_addr as *mut c_void,
-            dx9_present_impl as *mut c_void,
-        )
-        .expect("couldn't create IDirect3DDevice9::Present hook");
-        let hook_reset = MhHook::new(dx9_reset_addr as *mut c_void, dx9_reset_impl as *mut c_void)
-            .expect("couldn't create IDirect3DDevice9::Reset hook");
+    pub unsafe fn try_new<T>(t: T) -> Result<Self, (HudhookError, T)>
+    where
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        match Self::create_hooks() {
+            Ok(hooks) => Ok(Self::install(hooks, RenderLoopSource::single(Box::new(t)))),
+            Err(e) => Err((e, t)),
+        }
+    }
 
-        RENDER_LOOP.get_or_init(|| Box::new(t));
-        TRAMPOLINES.get_or_init(|| {
-            Trampolines {
+    pub unsafe fn with_factory<F>(factory: F) -> Self
+    where
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        match Self::create_hooks() {
+            Ok(hooks) => Self::install(hooks, RenderLoopSource::factory(factory)),
+            Err(e) => panic!("couldn't create DirectX 9 hooks: {e}"),
+        }
+    }
+
+    unsafe fn create_hooks() -> Result<[MhHook; 2]> {
+        // Detours find their state through STATE, so a backend can only be hooked once.
+        if STATE.is_occupied() {
+            return Err(HudhookError::BackendInUse(Backend::Dx9));
+        }
+
+        let (dx9_present_addr, dx9_reset_addr) = get_target_addrs()?;
+
+        MhHook::new_all([
+            (
+                dx9_present_addr as *mut c_void,
+                dx9_present_impl as *mut c_void,
+            ),
+            (dx9_reset_addr as *mut c_void, dx9_reset_impl as *mut c_void),
+        ])
+    }
+
+    unsafe fn install([hook_present, hook_reset]: [MhHook; 2], source: RenderLoopSource) -> Self {
+        let enabled = Arc::new(AtomicBool::new(true));
+        let state = Arc::new(State {
+            trampolines: Trampolines {
                 dx9_present: mem::transmute::<*mut c_void, Dx9PresentType>(
                     hook_present.trampoline(),
                 ),
                 dx9_reset: mem::transmute::<*mut c_void, Dx9ResetType>(hook_reset.trampoline()),
-            }
+            },
+            pipelines: Mutex::new(PipelineMap::new(source, Arc::clone(&enabled))),
+            in_flight: InFlight::new(),
+            enabled,
         });
+        STATE.set(&state);
 
-        Self([hook_present, hook_reset])
+        Self {
+            hooks: [hook_present, hook_reset],
+            state,
+        }
     }
 }
 
@@ -236,13 +271,44 @@ impl Hooks for ImguiDx9Hooks {
         Box::new(unsafe { Self::new(t) })
     }
 
+    fn try_from_render_loop<T>(t: T) -> Result<Box<Self>, (HudhookError, T)>
+    where
+        Self: Sized,
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        unsafe { Self::try_new(t) }.map(Box::new)
+    }
+
+    fn from_render_loop_factory<F>(factory: F) -> Box<Self>
+    where
+        Self: Sized,
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        Box::new(unsafe { Self::with_factory(factory) })
+    }
+
     fn hooks(&self) -> &[MhHook] {
-        &self.0
+        &self.hooks
+    }
+
+    fn in_flight(&self) -> usize {
+        self.state.in_flight.count()
+    }
+
+    fn backend(&self) -> Option<Backend> {
+        Some(Backend::Dx9)
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.state.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    fn restore_wnd_procs(&self) -> usize {
+        self.state.pipelines.lock().restore_wnd_procs()
     }
 
     unsafe fn unhook(&mut self) {
-        TRAMPOLINES.take();
-        PIPELINE.take().map(|p| p.into_inner().take());
-        RENDER_LOOP.take();
+        STATE.clear(&self.state);
+        self.state.pipelines.lock().clear();
     }
 }
@@ -1,10 +1,25 @@
-use std::{mem, sync::OnceLock};
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+        OnceLock,
+    },
+};
 
+use parking_lot::RwLock;
 use windows::{
-    core::w,
+    core::{w, HSTRING, PCWSTR},
     Win32::{
-        Foundation::{BOOL, HWND, LPARAM, LRESULT, WPARAM},
-        System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentProcessId},
+        Foundation::{BOOL, HINSTANCE, HMODULE, HWND, LPARAM, LRESULT, WPARAM},
+        System::{
+            LibraryLoader::{
+                GetModuleHandleExW,
+                GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS,
+                GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            },
+            Threading::GetCurrentProcessId,
+        },
         UI::WindowsAndMessaging::{
             CreateWindowExW,
             DefWindowProcW,
@@ -22,6 +37,7 @@ use windows::{
     },
 };
 
+pub mod detect;
 #[cfg(feature = "dx11")]
 pub mod dx11;
 #[cfg(feature = "dx12")]
@@ -31,6 +47,35 @@ pub mod dx9;
 #[cfg(feature = "opengl3")]
 pub mod opengl3;
 
+// Detours are plain functions, so they reach the state of the hooks instance that installed them
+// through a slot. Unhooking only clears the slot if it still holds that instance's state.
+pub(crate) struct InstanceSlot<S>(RwLock<Option<Arc<S>>>);
+
+impl<S> InstanceSlot<S> {
+    pub(crate) const fn new() -> Self {
+        Self(parking_lot::const_rwlock(None))
+    }
+
+    pub(crate) fn get(&self) -> Option<Arc<S>> {
+        self.0.read().clone()
+    }
+
+    pub(crate) fn is_occupied(&self) -> bool {
+        self.0.read().is_some()
+    }
+
+    pub(crate) fn set(&self, state: &Arc<S>) {
+        *self.0.write() = Some(Arc::clone(state));
+    }
+
+    pub(crate) fn clear(&self, state: &Arc<S>) {
+        let mut slot = self.0.write();
+        if slot.as_ref().is_some_and(|s| Arc::ptr_eq(s, state)) {
+            *slot = None;
+        }
+    }
+}
+
 pub fn find_process_hwnd() -> Option<HWND> {
     static mut FOUND_HWND: OnceLock<HWND> = OnceLock::new();
 
@@ -53,7 +98,7 @@ pub fn find_process_hwnd() -> Option<HWND> {
     unsafe { FOUND_HWND.get().copied() }
 }
 
-pub struct DummyHwnd(HWND, WNDCLASSEXW);
+pub struct DummyHwnd(HWND, WNDCLASSEXW, HSTRING);
 
 impl Default for DummyHwnd {
     fn default() -> Self {
@@ -72,14 +117,34 @@ impl DummyHwnd {
             DefWindowProcW(hwnd, msg, wparam, lparam)
         }
 
+        static CLASS_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        // Register the class against our own module under a name no other copy of this crate in
+        // the process can pick, so overlays don't unregister each other's classes.
+        let mut module = HMODULE::default();
+        unsafe {
+            let _ = GetModuleHandleExW(
+                GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS
+                    | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+                PCWSTR(wnd_proc as *const u16),
+                &mut module,
+            );
+        }
+
+        let class_name = HSTRING::from(format!(
+            "HUDHOOK_{:x}_{}",
+            module.0,
+            CLASS_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+
         let wndclass = WNDCLASSEXW {
             cbSize: mem::size_of::<WNDCLASSEXW>() as u32,
             style: CS_HREDRAW | CS_VREDRAW,
             lpfnWndProc: Some(wnd_proc),
             cbClsExtra: 0,
             cbWndExtra: 0,
-            hInstance: unsafe { GetModuleHandleW(None).unwrap().into() },
-            lpszClassName: w!("HUDHOOK"),
+            hInstance: HINSTANCE::from(module),
+            lpszClassName: PCWSTR(class_name.as_ptr()),
             ..Default::default()
         };
 
@@ -102,7 +167,7 @@ impl DummyHwnd {
             )
         };
 
-        Self(hwnd, wndclass)
+        Self(hwnd, wndclass, class_name)
     }
 
     pub fn hwnd(&self) -> HWND {
@@ -114,7 +179,7 @@ impl Drop for DummyHwnd {
     fn drop(&mut self) {
         unsafe {
             let _ = DestroyWindow(self.0);
-            let _ = UnregisterClassW(self.1.lpszClassName, self.1.hInstance);
+            let _ = UnregisterClassW(&self.2, self.1.hInstance);
         }
     }
 }
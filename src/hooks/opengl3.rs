@@ -1,23 +1,25 @@
 use std::{
-    ffi::{c_void, CString},
+    ffi::c_void,
     mem,
-    sync::OnceLock,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
-use imgui::Context;
-use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
-use windows::{
-    core::{Error, Result, HRESULT, PCSTR},
-    Win32::{
-        Graphics::Gdi::{WindowFromDC, HDC},
-        System::LibraryLoader::{GetModuleHandleA, GetProcAddress},
-    },
+use windows::Win32::{
+    Foundation::HWND,
+    Graphics::Gdi::{WindowFromDC, HDC},
 };
 
+use super::InstanceSlot;
 use crate::{
-    mh::MhHook,
-    renderer::{OpenGl3RenderEngine, Pipeline},
+    error::{HudhookError, Result},
+    mh::{self, Export, MhHook},
+    renderer::{OpenGl3RenderEngine, PipelineMap, RenderLoopSource, RENDER_LOCK_TIMEOUT},
+    util::InFlight,
+    Backend,
     Hooks,
     ImguiRenderLoop,
 };
@@ -28,93 +30,120 @@ struct Trampolines {
     opengl32_wgl_swap_buffers: OpenGl32wglSwapBuffersType,
 }
 
-static mut TRAMPOLINES: OnceLock<Trampolines> = OnceLock::new();
-static mut PIPELINE: OnceCell<Mutex<Pipeline<OpenGl3RenderEngine>>> = OnceCell::new();
-static mut RENDER_LOOP: OnceCell<Box<dyn ImguiRenderLoop + Send + Sync>> = OnceCell::new();
-
-unsafe fn init_pipeline(dc: HDC) -> Result<Mutex<Pipeline<OpenGl3RenderEngine>>> {
-    let hwnd = WindowFromDC(dc);
-
-    let mut ctx = Context::create();
-    let engine = OpenGl3RenderEngine::new(&mut ctx)?;
-
-    let Some(render_loop) = RENDER_LOOP.take() else {
-        return Err(Error::from_hresult(HRESULT(-1)));
-    };
+struct State {
+    trampolines: Trampolines,
+    pipelines: Mutex<PipelineMap<OpenGl3RenderEngine>>,
+    in_flight: InFlight,
+    enabled: Arc<AtomicBool>,
+}
 
-    let pipeline = Pipeline::new(hwnd, ctx, engine, render_loop).map_err(|(e, render_loop)| {
-        RENDER_LOOP.get_or_init(move || render_loop);
-        e
-    })?;
+unsafe impl Send for State {}
+unsafe impl Sync for State {}
 
-    Ok(Mutex::new(pipeline))
-}
+static STATE: InstanceSlot<State> = InstanceSlot::new();
 
-fn render(dc: HDC) -> Result<()> {
+fn render(state: &State, dc: HDC) -> Result<()> {
     unsafe {
-        let pipeline = PIPELINE.get_or_try_init(|| init_pipeline(dc))?;
-
-        let Some(mut pipeline) = pipeline.try_lock() else {
-            return Err(Error::from_hresult(HRESULT(-1)));
+        let Some(mut pipelines) = state.pipelines.try_lock_for(RENDER_LOCK_TIMEOUT) else {
+            return Err(HudhookError::LockContention);
         };
 
-        pipeline.prepare_render()?;
-
-        pipeline.render(())?;
+        pipelines.render(WindowFromDC(dc), OpenGl3RenderEngine::new, |pipeline| {
+            pipeline.prepare_render()?;
+            pipeline.render(())
+        })
     }
-
-    Ok(())
 }
 
 unsafe extern "system" fn opengl32_wgl_swap_buffers_impl(dc: HDC) {
-    let Trampolines {
-        opengl32_wgl_swap_buffers,
-    } = TRAMPOLINES
-        .get()
-        .expect("OpenGL3 trampolines uninitialized");
+    let state = STATE.get().expect("OpenGL3 hooks uninitialized");
+    let _in_flight = state.in_flight.enter();
 
-    let _ = render(dc);
+    let _ = render(&state, dc);
 
-    opengl32_wgl_swap_buffers(dc);
+    (state.trampolines.opengl32_wgl_swap_buffers)(dc);
 }
 
-unsafe fn get_opengl_wglswapbuffers_addr() -> OpenGl32wglSwapBuffersType {
-    let opengl32dll = CString::new("opengl32.dll").unwrap();
-    let opengl32module = GetModuleHandleA(PCSTR(opengl32dll.as_ptr() as *mut _))
-        .expect("failed finding opengl32.dll");
-
-    let wglswapbuffers = CString::new("wglSwapBuffers").unwrap();
+unsafe fn get_opengl_wglswapbuffers_addr() -> Result<OpenGl32wglSwapBuffersType> {
     let wglswapbuffers_func =
-        GetProcAddress(opengl32module, PCSTR(wglswapbuffers.as_ptr() as *mut _)).unwrap();
+        mh::find_export("opengl32.dll", Export::Name("wglSwapBuffers"), None)?;
 
-    mem::transmute::<unsafe extern "system" fn() -> isize, OpenGl32wglSwapBuffersType>(
+    Ok(mem::transmute::<*mut c_void, OpenGl32wglSwapBuffersType>(
         wglswapbuffers_func,
-    )
+    ))
 }
 
-pub struct ImguiOpenGl3Hooks([MhHook; 1]);
+pub struct ImguiOpenGl3Hooks {
+    hooks: [MhHook; 1],
+    state: Arc<State>,
+}
 
 impl ImguiOpenGl3Hooks {
     pub unsafe fn new<T>(t: T) -> Self
     where
         T: ImguiRenderLoop + Send + Sync + 'static,
     {
-        let hook_opengl_swap_buffers_address = get_opengl_wglswapbuffers_addr();
+        match Self::try_new(t) {
+            Ok(hooks) => hooks,
+            Err((e, _)) => panic!("couldn't create OpenGL3 hooks: {e}"),
+        }
+    }
+
+    pub unsafe fn try_new<T>(t: T) -> Result<Self, (HudhookError, T)>
+    where
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        match Self::create_hooks() {
+            Ok(hooks) => Ok(Self::install(hooks, RenderLoopSource::single(Box::new(t)))),
+            Err(e) => Err((e, t)),
+        }
+    }
+
+    pub unsafe fn with_factory<F>(factory: F) -> Self
+    where
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        match Self::create_hooks() {
+            Ok(hooks) => Self::install(hooks, RenderLoopSource::factory(factory)),
+            Err(e) => panic!("couldn't create OpenGL3 hooks: {e}"),
+        }
+    }
+
+    unsafe fn create_hooks() -> Result<[MhHook; 1]> {
+        // Detours find their state through STATE, so a backend can only be hooked once.
+        if STATE.is_occupied() {
+            return Err(HudhookError::BackendInUse(Backend::OpenGl3));
+        }
 
-        let hook_opengl_wgl_swap_buffers = MhHook::new(
+        let hook_opengl_swap_buffers_address = get_opengl_wglswapbuffers_addr()?;
+
+        MhHook::new_all([(
             hook_opengl_swap_buffers_address as *mut _,
             opengl32_wgl_swap_buffers_impl as *mut _,
-        )
-        .expect("couldn't create opengl32.wglSwapBuffers hook");
-
-        RENDER_LOOP.get_or_init(move || Box::new(t));
-        TRAMPOLINES.get_or_init(|| Trampolines {
-            opengl32_wgl_swap_buffers: mem::transmute::<*mut c_void, OpenGl32wglSwapBuffersType>(
-                hook_opengl_wgl_swap_buffers.trampoline(),
-            ),
+        )])
+    }
+
+    unsafe fn install(
+        [hook_opengl_wgl_swap_buffers]: [MhHook; 1],
+        source: RenderLoopSource,
+    ) -> Self {
+        let enabled = Arc::new(AtomicBool::new(true));
+        let state = Arc::new(State {
+            trampolines: Trampolines {
+                opengl32_wgl_swap_buffers: mem::transmute::<*mut c_void, OpenGl32wglSwapBuffersType>(
+                    hook_opengl_wgl_swap_buffers.trampoline(),
+                ),
+            },
+            pipelines: Mutex::new(PipelineMap::new(source, Arc::clone(&enabled))),
+            in_flight: InFlight::new(),
+            enabled,
         });
+        STATE.set(&state);
 
-        Self([hook_opengl_wgl_swap_buffers])
+        Self {
+            hooks: [hook_opengl_wgl_swap_buffers],
+            state,
+        }
     }
 }
 
@@ -127,13 +156,44 @@ impl Hooks for ImguiOpenGl3Hooks {
         Box::new(unsafe { ImguiOpenGl3Hooks::new(t) })
     }
 
+    fn try_from_render_loop<T>(t: T) -> Result<Box<Self>, (HudhookError, T)>
+    where
+        Self: Sized,
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        unsafe { ImguiOpenGl3Hooks::try_new(t) }.map(Box::new)
+    }
+
+    fn from_render_loop_factory<F>(factory: F) -> Box<Self>
+    where
+        Self: Sized,
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        Box::new(unsafe { ImguiOpenGl3Hooks::with_factory(factory) })
+    }
+
     fn hooks(&self) -> &[MhHook] {
-        &self.0
+        &self.hooks
+    }
+
+    fn in_flight(&self) -> usize {
+        self.state.in_flight.count()
+    }
+
+    fn backend(&self) -> Option<Backend> {
+        Some(Backend::OpenGl3)
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.state.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    fn restore_wnd_procs(&self) -> usize {
+        self.state.pipelines.lock().restore_wnd_procs()
     }
 
     unsafe fn unhook(&mut self) {
-        TRAMPOLINES.take();
-        PIPELINE.take().map(|p| p.into_inner().take());
-        RENDER_LOOP.take();
+        STATE.clear(&self.state);
+        self.state.pipelines.lock().clear();
     }
 }
@@ -0,0 +1,215 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use windows::Win32::{
+    Foundation::{LPARAM, WPARAM},
+    UI::{
+        Input::KeyboardAndMouse::{GetKeyState, VIRTUAL_KEY, VK_CONTROL, VK_MENU, VK_SHIFT},
+        WindowsAndMessaging::{WM_KEYDOWN, WM_SYSKEYDOWN},
+    },
+};
+
+type Hotkey = (HotkeyId, Chord, Arc<HotkeyAction>);
+
+static HOTKEYS: Lazy<Mutex<Vec<Hotkey>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HotkeyId(u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    key: VIRTUAL_KEY,
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+}
+
+impl Chord {
+    pub fn new(key: VIRTUAL_KEY) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    pub fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    pub fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    pub fn matches(&self, key: VIRTUAL_KEY, ctrl: bool, shift: bool, alt: bool) -> bool {
+        self.key == key && self.ctrl == ctrl && self.shift == shift && self.alt == alt
+    }
+}
+
+pub enum HotkeyAction {
+    ToggleVisibility,
+    ToggleInputCapture,
+    Eject,
+    Custom(Box<dyn Fn() + Send + Sync>),
+}
+
+impl HotkeyAction {
+    fn run(&self) {
+        match self {
+            HotkeyAction::ToggleVisibility => crate::set_overlay_visible(!crate::overlay_visible()),
+            HotkeyAction::ToggleInputCapture => {
+                crate::set_input_capture_enabled(!crate::input_capture_enabled())
+            }
+            HotkeyAction::Eject => crate::eject(),
+            HotkeyAction::Custom(f) => f(),
+        }
+    }
+}
+
+pub fn register(chord: Chord, action: HotkeyAction) -> HotkeyId {
+    let id = HotkeyId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    HOTKEYS.lock().push((id, chord, Arc::new(action)));
+    id
+}
+
+pub fn unregister(id: HotkeyId) {
+    HOTKEYS.lock().retain(|(hotkey, ..)| *hotkey != id);
+}
+
+pub fn clear() {
+    HOTKEYS.lock().clear();
+}
+
+pub(crate) fn handle_message(umsg: u32, wparam: WPARAM, lparam: LPARAM) -> bool {
+    if umsg != WM_KEYDOWN && umsg != WM_SYSKEYDOWN {
+        return false;
+    }
+
+    if (lparam.0 >> 30) & 1 == 1 {
+        return false;
+    }
+
+    let key = VIRTUAL_KEY(wparam.0 as u16);
+    let (ctrl, shift, alt) = unsafe {
+        (
+            GetKeyState(VK_CONTROL.0 as i32) < 0,
+            GetKeyState(VK_SHIFT.0 as i32) < 0,
+            GetKeyState(VK_MENU.0 as i32) < 0,
+        )
+    };
+
+    // Actions run after the lock is released, so they can register or clear hotkeys themselves.
+    let actions = {
+        let Some(hotkeys) = HOTKEYS.try_lock() else {
+            return false;
+        };
+
+        hotkeys
+            .iter()
+            .filter(|(_, chord, _)| chord.matches(key, ctrl, shift, alt))
+            .map(|(_, _, action)| Arc::clone(action))
+            .collect::<Vec<_>>()
+    };
+
+    for action in &actions {
+        action.run();
+    }
+
+    !actions.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use windows::Win32::UI::Input::KeyboardAndMouse::{VK_F10, VK_F11, VK_F7, VK_F8, VK_F9};
+
+    use super::*;
+
+    const REPEAT: isize = 1 << 30;
+
+    fn counter(hits: &Arc<AtomicUsize>) -> HotkeyAction {
+        let hits = Arc::clone(hits);
+        HotkeyAction::Custom(Box::new(move || {
+            hits.fetch_add(1, Ordering::SeqCst);
+        }))
+    }
+
+    // Tests share the registry, so each one binds its own keys.
+    fn key_down(key: VIRTUAL_KEY, lparam: isize) -> bool {
+        handle_message(WM_KEYDOWN, WPARAM(key.0 as usize), LPARAM(lparam))
+    }
+
+    #[test]
+    fn chords_match_exact_modifiers() {
+        let chord = Chord::new(VK_F9).ctrl().shift();
+
+        assert!(chord.matches(VK_F9, true, true, false));
+        assert!(!chord.matches(VK_F9, true, false, false));
+        assert!(!chord.matches(VK_F9, true, true, true));
+        assert!(!chord.matches(VK_F8, true, true, false));
+        assert!(Chord::new(VK_F9).alt().matches(VK_F9, false, false, true));
+        assert!(!Chord::new(VK_F9).matches(VK_F9, false, false, true));
+    }
+
+    #[test]
+    fn ignores_auto_repeat() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let id = register(Chord::new(VK_F7), counter(&hits));
+
+        assert!(!key_down(VK_F7, REPEAT));
+        assert!(key_down(VK_F7, 0));
+        unregister(id);
+
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn unregister_keeps_other_bindings_of_the_chord() {
+        let first = Arc::new(AtomicUsize::new(0));
+        let second = Arc::new(AtomicUsize::new(0));
+        let id = register(Chord::new(VK_F8), counter(&first));
+        let other = register(Chord::new(VK_F8), counter(&second));
+
+        unregister(id);
+        assert!(key_down(VK_F8, 0));
+        unregister(other);
+
+        assert_eq!(first.load(Ordering::SeqCst), 0);
+        assert_eq!(second.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn actions_can_register_hotkeys() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let registered = Arc::new(Mutex::new(None));
+        let id = register(
+            Chord::new(VK_F10),
+            HotkeyAction::Custom(Box::new({
+                let hits = Arc::clone(&hits);
+                let registered = Arc::clone(&registered);
+                move || *registered.lock() = Some(register(Chord::new(VK_F11), counter(&hits)))
+            })),
+        );
+
+        assert!(key_down(VK_F10, 0));
+        assert!(key_down(VK_F11, 0));
+        unregister(id);
+        unregister(registered.lock().take().unwrap());
+
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+}
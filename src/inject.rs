@@ -0,0 +1,695 @@
+use std::{
+    env,
+    ffi::{c_void, OsStr, OsString},
+    fmt,
+    iter,
+    mem,
+    path::{Path, PathBuf},
+};
+
+use windows::{
+    core::{s, w, PCWSTR, PWSTR},
+    Win32::{
+        Foundation::{CloseHandle, BOOL, HANDLE, HMODULE, HWND, LPARAM},
+        System::{
+            Diagnostics::{
+                Debug::WriteProcessMemory,
+                ToolHelp::{
+                    CreateToolhelp32Snapshot,
+                    Module32FirstW,
+                    Module32NextW,
+                    Process32FirstW,
+                    Process32NextW,
+                    MODULEENTRY32W,
+                    PROCESSENTRY32W,
+                    TH32CS_SNAPMODULE,
+                    TH32CS_SNAPMODULE32,
+                    TH32CS_SNAPPROCESS,
+                },
+            },
+            LibraryLoader::{GetModuleHandleW, GetProcAddress},
+            Memory::{
+                VirtualAllocEx,
+                VirtualFreeEx,
+                MEM_COMMIT,
+                MEM_RELEASE,
+                MEM_RESERVE,
+                PAGE_READWRITE,
+            },
+            Threading::{
+                CreateProcessW,
+                CreateRemoteThread,
+                GetCurrentProcess,
+                GetExitCodeProcess,
+                IsWow64Process,
+                OpenProcess,
+                ResumeThread,
+                TerminateProcess,
+                WaitForSingleObject,
+                CREATE_SUSPENDED,
+                CREATE_UNICODE_ENVIRONMENT,
+                INFINITE,
+                PROCESS_CREATE_THREAD,
+                PROCESS_INFORMATION,
+                PROCESS_QUERY_INFORMATION,
+                PROCESS_VM_OPERATION,
+                PROCESS_VM_READ,
+                PROCESS_VM_WRITE,
+                STARTUPINFOW,
+            },
+        },
+        UI::WindowsAndMessaging::{EnumWindows, GetWindowTextW, GetWindowThreadProcessId},
+    },
+};
+
+use crate::error::{HudhookError, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub pid: u32,
+    pub title: String,
+}
+
+pub trait ProcessList {
+    fn processes(&self) -> Result<Vec<ProcessInfo>>;
+    fn windows(&self) -> Result<Vec<WindowInfo>>;
+}
+
+pub struct SystemProcessList;
+
+impl ProcessList for SystemProcessList {
+    fn processes(&self) -> Result<Vec<ProcessInfo>> {
+        let snapshot = Handle(unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) }?);
+
+        let mut entry = PROCESSENTRY32W {
+            dwSize: mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut processes = Vec::new();
+
+        let mut next = unsafe { Process32FirstW(snapshot.0, &mut entry) };
+        while next.is_ok() {
+            processes.push(ProcessInfo {
+                pid: entry.th32ProcessID,
+                name: from_wide(&entry.szExeFile),
+            });
+            next = unsafe { Process32NextW(snapshot.0, &mut entry) };
+        }
+
+        Ok(processes)
+    }
+
+    fn windows(&self) -> Result<Vec<WindowInfo>> {
+        unsafe extern "system" fn enum_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            let windows = &mut *(lparam.0 as *mut Vec<WindowInfo>);
+
+            let mut title = [0u16; 512];
+            let len = GetWindowTextW(hwnd, &mut title);
+            if len > 0 {
+                let mut pid = 0;
+                GetWindowThreadProcessId(hwnd, Some(&mut pid));
+                windows.push(WindowInfo {
+                    pid,
+                    title: from_wide(&title[..len as usize]),
+                });
+            }
+
+            BOOL::from(true)
+        }
+
+        let mut windows: Vec<WindowInfo> = Vec::new();
+        unsafe { EnumWindows(Some(enum_callback), LPARAM(&mut windows as *mut _ as isize)) }?;
+
+        Ok(windows)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessTarget {
+    Pid(u32),
+    Name(String),
+    WindowTitle(String),
+}
+
+impl ProcessTarget {
+    pub fn resolve(&self, list: &impl ProcessList) -> Result<u32> {
+        let mut pids: Vec<u32> = match self {
+            ProcessTarget::Pid(pid) => list
+                .processes()?
+                .into_iter()
+                .filter(|process| process.pid == *pid)
+                .map(|process| process.pid)
+                .collect(),
+            ProcessTarget::Name(name) => list
+                .processes()?
+                .into_iter()
+                .filter(|process| process.name.eq_ignore_ascii_case(name))
+                .map(|process| process.pid)
+                .collect(),
+            ProcessTarget::WindowTitle(title) => list
+                .windows()?
+                .into_iter()
+                .filter(|window| window.title == *title)
+                .map(|window| window.pid)
+                .collect(),
+        };
+
+        pids.sort_unstable();
+        pids.dedup();
+
+        match pids[..] {
+            [] => Err(HudhookError::ProcessNotFound(self.to_string())),
+            [pid] => Ok(pid),
+            _ => Err(HudhookError::AmbiguousProcess {
+                target: self.to_string(),
+                pids,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ProcessTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessTarget::Pid(pid) => write!(f, "process {pid}"),
+            ProcessTarget::Name(name) => write!(f, "process named {name:?}"),
+            ProcessTarget::WindowTitle(title) => write!(f, "window titled {title:?}"),
+        }
+    }
+}
+
+pub fn inject(pid: u32, dll_path: impl AsRef<Path>) -> Result<HMODULE> {
+    let dll_path = dll_path.as_ref().canonicalize()?;
+
+    let process = Handle(unsafe {
+        OpenProcess(
+            PROCESS_CREATE_THREAD
+                | PROCESS_QUERY_INFORMATION
+                | PROCESS_VM_OPERATION
+                | PROCESS_VM_READ
+                | PROCESS_VM_WRITE,
+            false,
+            pid,
+        )
+    }?);
+
+    load_library(process.0, &dll_path)?;
+    find_remote_module(pid, &dll_path)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    pub program: PathBuf,
+    pub args: Vec<OsString>,
+    pub current_dir: Option<PathBuf>,
+    pub env: Vec<(OsString, OsString)>,
+}
+
+pub struct LaunchedProcess {
+    process: Handle,
+    pid: u32,
+    module: HMODULE,
+}
+
+impl LaunchedProcess {
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn module(&self) -> HMODULE {
+        self.module
+    }
+
+    pub fn wait(&self) -> Result<u32> {
+        let mut exit_code = 0u32;
+        unsafe {
+            WaitForSingleObject(self.process.0, INFINITE);
+            GetExitCodeProcess(self.process.0, &mut exit_code)?;
+        }
+
+        Ok(exit_code)
+    }
+}
+
+pub fn launch(options: &LaunchOptions, dll_path: impl AsRef<Path>) -> Result<LaunchedProcess> {
+    let dll_path = dll_path.as_ref().canonicalize()?;
+
+    let application = to_wide(options.program.as_os_str());
+    let mut command_line = command_line(&options.program, &options.args);
+    let environment = (!options.env.is_empty()).then(|| environment_block(&options.env));
+    let current_dir = options
+        .current_dir
+        .as_ref()
+        .map(|dir| to_wide(dir.as_os_str()));
+
+    let startup_info = STARTUPINFOW {
+        cb: mem::size_of::<STARTUPINFOW>() as u32,
+        ..Default::default()
+    };
+    let mut process_info = PROCESS_INFORMATION::default();
+
+    unsafe {
+        CreateProcessW(
+            PCWSTR(application.as_ptr()),
+            PWSTR(command_line.as_mut_ptr()),
+            None,
+            None,
+            false,
+            CREATE_SUSPENDED | CREATE_UNICODE_ENVIRONMENT,
+            environment
+                .as_ref()
+                .map(|env| env.as_ptr() as *const c_void),
+            current_dir
+                .as_ref()
+                .map_or(PCWSTR::null(), |dir| PCWSTR(dir.as_ptr())),
+            &startup_info,
+            &mut process_info,
+        )
+    }?;
+
+    let process = Handle(process_info.hProcess);
+    let thread = Handle(process_info.hThread);
+    let pid = process_info.dwProcessId;
+
+    // The main thread must not run before our DLL is loaded, and must not be left suspended
+    // forever if injection fails.
+    let injected =
+        load_library(process.0, &dll_path).and_then(|_| find_remote_module(pid, &dll_path));
+    let module = match injected {
+        Ok(module) => module,
+        Err(e) => {
+            unsafe {
+                let _ = TerminateProcess(process.0, 1);
+            }
+            return Err(e);
+        }
+    };
+
+    if unsafe { ResumeThread(thread.0) } == u32::MAX {
+        let e = windows::core::Error::from_win32();
+        unsafe {
+            let _ = TerminateProcess(process.0, 1);
+        }
+        return Err(e.into());
+    }
+
+    Ok(LaunchedProcess {
+        process,
+        pid,
+        module,
+    })
+}
+
+fn to_wide(s: &OsStr) -> Vec<u16> {
+    encode_wide(s).chain(iter::once(0)).collect()
+}
+
+#[cfg(windows)]
+fn encode_wide(s: &OsStr) -> impl Iterator<Item = u16> + '_ {
+    std::os::windows::ffi::OsStrExt::encode_wide(s)
+}
+
+// Lets everything that doesn't call into Win32 build and be tested on other hosts.
+#[cfg(not(windows))]
+fn encode_wide(s: &OsStr) -> impl Iterator<Item = u16> + '_ {
+    s.to_string_lossy()
+        .encode_utf16()
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+// Quotes arguments the way CommandLineToArgvW and the MSVC runtime split them.
+fn command_line(program: &Path, args: &[OsString]) -> Vec<u16> {
+    let mut command_line = Vec::new();
+
+    for (i, arg) in iter::once(program.as_os_str())
+        .chain(args.iter().map(OsString::as_os_str))
+        .enumerate()
+    {
+        if i > 0 {
+            command_line.push(b' ' as u16);
+        }
+
+        let arg: Vec<u16> = encode_wide(arg).collect();
+        let quote = arg.is_empty()
+            || arg
+                .iter()
+                .any(|&c| c == b' ' as u16 || c == b'\t' as u16 || c == b'"' as u16);
+
+        if !quote {
+            command_line.extend(arg);
+            continue;
+        }
+
+        command_line.push(b'"' as u16);
+        let mut backslashes = 0;
+        for c in arg {
+            if c == b'\\' as u16 {
+                backslashes += 1;
+            } else {
+                if c == b'"' as u16 {
+                    command_line.extend(iter::repeat_n(b'\\' as u16, backslashes + 1));
+                }
+                backslashes = 0;
+            }
+            command_line.push(c);
+        }
+        command_line.extend(iter::repeat_n(b'\\' as u16, backslashes));
+        command_line.push(b'"' as u16);
+    }
+
+    command_line.push(0);
+    command_line
+}
+
+fn environment_block(overrides: &[(OsString, OsString)]) -> Vec<u16> {
+    let mut vars: Vec<(OsString, OsString)> = env::vars_os().collect();
+
+    for (key, value) in overrides {
+        let key_str = key.to_string_lossy();
+        vars.retain(|(k, _)| !k.to_string_lossy().eq_ignore_ascii_case(&key_str));
+        vars.push((key.clone(), value.clone()));
+    }
+
+    let mut block = Vec::new();
+    for (key, value) in vars {
+        block.extend(encode_wide(&key));
+        block.push(b'=' as u16);
+        block.extend(encode_wide(&value));
+        block.push(0);
+    }
+    block.push(0);
+
+    block
+}
+
+// A WOW64 process has its own 32-bit kernel32.dll, so our LoadLibraryW address is only valid in
+// processes of the same bitness.
+fn check_bitness(process: HANDLE) -> Result<()> {
+    let mut ours = BOOL::default();
+    let mut theirs = BOOL::default();
+    unsafe {
+        IsWow64Process(GetCurrentProcess(), &mut ours)?;
+        IsWow64Process(process, &mut theirs)?;
+    }
+
+    if ours != theirs {
+        return Err(HudhookError::BitnessMismatch);
+    }
+
+    Ok(())
+}
+
+fn load_library(process: HANDLE, dll_path: &Path) -> Result<()> {
+    check_bitness(process)?;
+
+    let path = to_wide(dll_path.as_os_str());
+    let size = path.len() * mem::size_of::<u16>();
+
+    let remote_path = RemoteAlloc::new(process, size)?;
+    unsafe {
+        WriteProcessMemory(
+            process,
+            remote_path.0,
+            path.as_ptr() as *const c_void,
+            size,
+            None,
+        )
+    }?;
+
+    // kernel32.dll is mapped at the same address in every process of the same bitness.
+    let load_library_w = unsafe {
+        let kernel32 = GetModuleHandleW(w!("kernel32.dll"))?;
+        match GetProcAddress(kernel32, s!("LoadLibraryW")) {
+            Some(f) => mem::transmute::<
+                unsafe extern "system" fn() -> isize,
+                unsafe extern "system" fn(*mut c_void) -> u32,
+            >(f),
+            None => {
+                return Err(HudhookError::HookTargetNotFound(
+                    "kernel32.dll!LoadLibraryW",
+                ))
+            }
+        }
+    };
+
+    let thread = Handle(unsafe {
+        CreateRemoteThread(
+            process,
+            None,
+            0,
+            Some(load_library_w),
+            Some(remote_path.0),
+            0,
+            None,
+        )
+    }?);
+
+    // The exit code only holds the low half of the module handle, which can be zero for a loaded
+    // module, so callers confirm the load with `find_remote_module` instead.
+    unsafe { WaitForSingleObject(thread.0, INFINITE) };
+
+    Ok(())
+}
+
+fn find_remote_module(pid: u32, dll_path: &Path) -> Result<HMODULE> {
+    let snapshot =
+        Handle(unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, pid) }?);
+
+    let mut entry = MODULEENTRY32W {
+        dwSize: mem::size_of::<MODULEENTRY32W>() as u32,
+        ..Default::default()
+    };
+
+    let mut next = unsafe { Module32FirstW(snapshot.0, &mut entry) };
+    while next.is_ok() {
+        let module_path = PathBuf::from(from_wide(&entry.szExePath));
+        if module_path
+            .canonicalize()
+            .is_ok_and(|path| path == dll_path)
+        {
+            return Ok(entry.hModule);
+        }
+        next = unsafe { Module32NextW(snapshot.0, &mut entry) };
+    }
+
+    Err(HudhookError::RemoteLoadFailed(dll_path.to_path_buf()))
+}
+
+fn from_wide(s: &[u16]) -> String {
+    let len = s.iter().position(|&c| c == 0).unwrap_or(s.len());
+    String::from_utf16_lossy(&s[..len])
+}
+
+pub(crate) struct Handle(pub(crate) HANDLE);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+struct RemoteAlloc(*mut c_void, HANDLE);
+
+impl RemoteAlloc {
+    fn new(process: HANDLE, size: usize) -> Result<Self> {
+        let ptr = unsafe {
+            VirtualAllocEx(
+                process,
+                None,
+                size,
+                MEM_COMMIT | MEM_RESERVE,
+                PAGE_READWRITE,
+            )
+        };
+        if ptr.is_null() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+
+        Ok(Self(ptr, process))
+    }
+}
+
+impl Drop for RemoteAlloc {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = VirtualFreeEx(self.1, self.0, 0, MEM_RELEASE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockProcessList {
+        processes: Vec<ProcessInfo>,
+        windows: Vec<WindowInfo>,
+    }
+
+    impl ProcessList for MockProcessList {
+        fn processes(&self) -> Result<Vec<ProcessInfo>> {
+            Ok(self.processes.clone())
+        }
+
+        fn windows(&self) -> Result<Vec<WindowInfo>> {
+            Ok(self.windows.clone())
+        }
+    }
+
+    fn list() -> MockProcessList {
+        let process = |pid, name: &str| ProcessInfo {
+            pid,
+            name: name.to_string(),
+        };
+        let window = |pid, title: &str| WindowInfo {
+            pid,
+            title: title.to_string(),
+        };
+
+        MockProcessList {
+            processes: vec![
+                process(4, "System"),
+                process(100, "Game.exe"),
+                process(200, "helper.exe"),
+                process(300, "helper.exe"),
+            ],
+            windows: vec![
+                window(100, "Game"),
+                window(100, "Game"),
+                window(100, "Game Launcher"),
+                window(200, "Tool"),
+                window(300, "Tool"),
+            ],
+        }
+    }
+
+    #[test]
+    fn resolves_pids() {
+        assert_eq!(ProcessTarget::Pid(200).resolve(&list()).unwrap(), 200);
+    }
+
+    #[test]
+    fn reports_missing_targets() {
+        for target in [
+            ProcessTarget::Pid(999),
+            ProcessTarget::Name("missing.exe".to_string()),
+            ProcessTarget::WindowTitle("game".to_string()),
+        ] {
+            match target.resolve(&list()) {
+                Err(HudhookError::ProcessNotFound(message)) => {
+                    assert_eq!(message, target.to_string())
+                }
+                result => panic!("{target}: {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_ambiguous_targets() {
+        for target in [
+            ProcessTarget::Name("helper.exe".to_string()),
+            ProcessTarget::WindowTitle("Tool".to_string()),
+        ] {
+            match target.resolve(&list()) {
+                Err(HudhookError::AmbiguousProcess {
+                    pids,
+                    ..
+                }) => assert_eq!(pids, [200, 300]),
+                result => panic!("{target}: {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn matches_names_case_insensitively() {
+        for name in ["game.exe", "GAME.EXE", "Game.exe"] {
+            let target = ProcessTarget::Name(name.to_string());
+
+            assert_eq!(target.resolve(&list()).unwrap(), 100);
+        }
+        assert!(ProcessTarget::Name("Game".to_string())
+            .resolve(&list())
+            .is_err());
+    }
+
+    #[test]
+    fn dedups_windows_of_the_same_process() {
+        let target = ProcessTarget::WindowTitle("Game".to_string());
+
+        assert_eq!(target.resolve(&list()).unwrap(), 100);
+    }
+
+    fn quote(program: &str, args: &[&str]) -> String {
+        let args = args.iter().map(OsString::from).collect::<Vec<_>>();
+        let command_line = command_line(Path::new(program), &args);
+
+        assert_eq!(command_line.last(), Some(&0));
+        String::from_utf16(&command_line[..command_line.len() - 1]).unwrap()
+    }
+
+    #[test]
+    fn leaves_plain_arguments_unquoted() {
+        assert_eq!(
+            quote("game.exe", &["-w", r"C:\saves\"]),
+            r"game.exe -w C:\saves\"
+        );
+    }
+
+    #[test]
+    fn quotes_spaces_and_empty_arguments() {
+        assert_eq!(
+            quote(r"C:\Program Files\game.exe", &["hello world", "", "\t"]),
+            "\"C:\\Program Files\\game.exe\" \"hello world\" \"\" \"\t\""
+        );
+    }
+
+    #[test]
+    fn escapes_quotes() {
+        assert_eq!(
+            quote("game.exe", &[r#"say "hi""#, r#"a\"b"#]),
+            r#"game.exe "say \"hi\"" "a\\\"b""#
+        );
+    }
+
+    #[test]
+    fn doubles_trailing_backslashes_in_quoted_arguments() {
+        assert_eq!(
+            quote("game.exe", &[r"C:\save dir\", r"a b\\"]),
+            r#"game.exe "C:\save dir\\" "a b\\\\""#
+        );
+    }
+
+    fn entries(block: &[u16]) -> Vec<String> {
+        assert!(block.ends_with(&[0, 0]));
+        String::from_utf16(&block[..block.len() - 2])
+            .unwrap()
+            .split('\0')
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn overrides_environment_variables_case_insensitively() {
+        let block = environment_block(&[
+            ("path".into(), "override".into()),
+            ("HUDHOOK_INJECT_TEST".into(), "1".into()),
+        ]);
+        let entries = entries(&block);
+
+        let paths = entries
+            .iter()
+            .filter(|entry| entry.to_ascii_lowercase().starts_with("path="))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["path=override"]);
+        assert!(entries.iter().any(|entry| entry == "HUDHOOK_INJECT_TEST=1"));
+    }
+}
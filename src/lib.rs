@@ -1,46 +1,74 @@
 use std::{
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Arc,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
+use hotkeys::{Chord, HotkeyAction, HotkeyId};
 pub use imgui;
-use imgui::{Context, TextureId, Ui};
-use mh::MhHook;
-use minhook_raw::sys::{MH_ApplyQueued, MH_Initialize, MH_Uninitialize, MH_STATUS};
+use imgui::{ClipboardBackend, Context, TextureId, Ui};
+use mh::{HookFn, MhHook, SharedHook, TypedHook};
+use minhook_raw::sys::{MH_ApplyQueued, MH_STATUS};
 use once_cell::sync::OnceCell;
+use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
 pub use windows;
-use windows::{
-    core::Error,
-    Win32::{
-        Foundation::HINSTANCE,
-        System::{
-            Console::{
-                AllocConsole,
-                FreeConsole,
-                GetConsoleMode,
-                GetStdHandle,
-                SetConsoleMode,
-                CONSOLE_MODE,
-                ENABLE_VIRTUAL_TERMINAL_PROCESSING,
-                STD_OUTPUT_HANDLE,
-            },
-            LibraryLoader::FreeLibraryAndExitThread,
+use windows::Win32::{
+    Foundation::{HINSTANCE, HWND},
+    System::{
+        Console::{
+            AllocConsole,
+            FreeConsole,
+            GetConsoleMode,
+            GetStdHandle,
+            SetConsoleMode,
+            CONSOLE_MODE,
+            ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+            STD_OUTPUT_HANDLE,
         },
+        LibraryLoader::{DisableThreadLibraryCalls, FreeLibraryAndExitThread},
     },
 };
 
+pub mod clipboard;
+pub mod error;
 pub mod hooks;
+pub mod hotkeys;
+pub mod inject;
 pub mod mh;
 pub(crate) mod renderer;
+pub mod replay;
+pub mod scan;
+
+pub use error::HudhookError;
+pub use hooks::detect::Backend;
+pub use renderer::{FrameTiming, InputPolicy, MessageQueueStats};
 
 pub mod util;
 
 static mut MODULE: OnceCell<HINSTANCE> = OnceCell::new();
-static mut HUDHOOK: OnceCell<Hudhook> = OnceCell::new();
+static HUDHOOK: Mutex<Option<Hudhook>> = parking_lot::const_mutex(None);
 static CONSOLE_ALLOCATED: AtomicBool = AtomicBool::new(false);
+static INPUT_POLICY: AtomicU8 = AtomicU8::new(InputPolicy::PassThrough as u8);
+static RAW_INPUT_CAPTURE: AtomicBool = AtomicBool::new(false);
+static OVERLAY_VISIBLE: AtomicBool = AtomicBool::new(true);
+static INPUT_CAPTURE_ENABLED: AtomicBool = AtomicBool::new(true);
+static DPI_AUTO_SCALE: AtomicBool = AtomicBool::new(false);
+static SOFTWARE_CURSOR: AtomicBool = AtomicBool::new(false);
+
+const DLL_MAIN_AUTO_WAIT: Duration = Duration::from_secs(10);
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
 
 pub trait RenderContext {
-    fn load_texture(&mut self, data: &[u8], width: u32, height: u32) -> Result<TextureId, Error>;
+    fn load_texture(
+        &mut self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<TextureId, HudhookError>;
 
     fn replace_texture(
         &mut self,
@@ -48,10 +76,10 @@ pub trait RenderContext {
         data: &[u8],
         width: u32,
         height: u32,
-    ) -> Result<(), Error>;
+    ) -> Result<(), HudhookError>;
 }
 
-pub fn alloc_console() -> Result<(), Error> {
+pub fn alloc_console() -> Result<(), HudhookError> {
     if !CONSOLE_ALLOCATED.swap(true, Ordering::SeqCst) {
         unsafe { AllocConsole()? };
     }
@@ -74,7 +102,7 @@ pub fn enable_console_colors() {
     }
 }
 
-pub fn free_console() -> Result<(), Error> {
+pub fn free_console() -> Result<(), HudhookError> {
     if CONSOLE_ALLOCATED.swap(false, Ordering::SeqCst) {
         unsafe { FreeConsole()? };
     }
@@ -82,12 +110,80 @@ pub fn free_console() -> Result<(), Error> {
     Ok(())
 }
 
+pub fn input_policy() -> InputPolicy {
+    InputPolicy::from_u8(INPUT_POLICY.load(Ordering::SeqCst))
+}
+
+pub fn set_input_policy(policy: InputPolicy) {
+    INPUT_POLICY.store(policy as u8, Ordering::SeqCst);
+}
+
+pub(crate) fn active_input_policy() -> InputPolicy {
+    if overlay_visible() && input_capture_enabled() {
+        input_policy()
+    } else {
+        InputPolicy::PassThrough
+    }
+}
+
+pub fn overlay_visible() -> bool {
+    OVERLAY_VISIBLE.load(Ordering::SeqCst)
+}
+
+pub fn set_overlay_visible(visible: bool) {
+    OVERLAY_VISIBLE.store(visible, Ordering::SeqCst);
+}
+
+pub fn input_capture_enabled() -> bool {
+    INPUT_CAPTURE_ENABLED.load(Ordering::SeqCst)
+}
+
+pub fn set_input_capture_enabled(enabled: bool) {
+    INPUT_CAPTURE_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn dpi_auto_scale() -> bool {
+    DPI_AUTO_SCALE.load(Ordering::SeqCst)
+}
+
+pub fn set_dpi_auto_scale(enabled: bool) {
+    DPI_AUTO_SCALE.store(enabled, Ordering::SeqCst);
+}
+
+pub fn software_cursor() -> bool {
+    SOFTWARE_CURSOR.load(Ordering::SeqCst)
+}
+
+pub fn set_software_cursor(enabled: bool) {
+    SOFTWARE_CURSOR.store(enabled, Ordering::SeqCst);
+}
+
+pub fn raw_input_capture() -> bool {
+    RAW_INPUT_CAPTURE.load(Ordering::SeqCst)
+}
+
+pub fn set_raw_input_capture(capture: bool) {
+    RAW_INPUT_CAPTURE.store(capture, Ordering::SeqCst);
+}
+
+pub fn message_queue_stats() -> MessageQueueStats {
+    renderer::message_queue_stats()
+}
+
+pub fn hudhook() -> Option<MappedMutexGuard<'static, Hudhook>> {
+    MutexGuard::try_map(HUDHOOK.lock(), Option::as_mut).ok()
+}
+
 pub fn eject() {
     thread::spawn(|| unsafe {
         let _ = free_console();
 
-        if let Some(mut hudhook) = HUDHOOK.take() {
-            let _ = hudhook.unapply();
+        let hudhook = HUDHOOK.lock().take();
+        if let Some(mut hudhook) = hudhook {
+            // Leaking the module is better than unmapping code another thread is still running.
+            if hudhook.unapply().is_err() {
+                return;
+            }
         }
 
         if let Some(module) = MODULE.take() {
@@ -111,6 +207,19 @@ pub trait ImguiRenderLoop {
     ) {
     }
 
+    fn on_frame_timing(&mut self, _timing: FrameTiming) {}
+
+    // With DPI auto-scaling the font atlas has just been cleared, including the fonts added in
+    // `initialize`, so they have to be added again here at `scale`. Only the default font is
+    // restored if none are.
+    fn on_dpi_scale_changed(&mut self, _ctx: &mut Context, _scale: f32) {}
+
+    fn on_focus_changed(&mut self, _focused: bool) {}
+
+    fn attach_to(&self, _hwnd: HWND) -> bool {
+        true
+    }
+
     unsafe fn render(&mut self, ui: &mut Ui);
 }
 
@@ -120,78 +229,318 @@ pub trait Hooks {
         Self: Sized,
         T: ImguiRenderLoop + Send + Sync + 'static;
 
+    fn try_from_render_loop<T>(t: T) -> Result<Box<Self>, (HudhookError, T)>
+    where
+        Self: Sized,
+        T: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        Ok(Self::from_render_loop(t))
+    }
+
+    fn from_render_loop_factory<F>(factory: F) -> Box<Self>
+    where
+        Self: Sized,
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        Self::from_render_loop(FirstWindow {
+            factory,
+            render_loop: Mutex::new(None),
+        })
+    }
+
     fn hooks(&self) -> &[MhHook];
 
+    fn in_flight(&self) -> usize {
+        0
+    }
+
+    fn backend(&self) -> Option<Backend> {
+        None
+    }
+
+    fn set_enabled(&self, _enabled: bool) {}
+
+    fn restore_wnd_procs(&self) -> usize {
+        0
+    }
+
     unsafe fn unhook(&mut self);
 }
 
-pub struct Hudhook(Vec<Box<dyn Hooks>>);
+// Lets hooks without per-window render loops take a factory: only the first window it accepts
+// gets a render loop.
+struct FirstWindow<F> {
+    factory: F,
+    render_loop: Mutex<Option<(isize, Box<dyn ImguiRenderLoop + Send + Sync>)>>,
+}
+
+impl<F> ImguiRenderLoop for FirstWindow<F>
+where
+    F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>>,
+{
+    unsafe fn initialize<'a>(
+        &'a mut self,
+        ctx: &mut Context,
+        render_context: &'a mut dyn RenderContext,
+    ) {
+        if let Some((_, render_loop)) = self.render_loop.get_mut() {
+            render_loop.initialize(ctx, render_context);
+        }
+    }
+
+    unsafe fn before_render<'a>(
+        &'a mut self,
+        ctx: &mut Context,
+        render_context: &'a mut dyn RenderContext,
+    ) {
+        if let Some((_, render_loop)) = self.render_loop.get_mut() {
+            render_loop.before_render(ctx, render_context);
+        }
+    }
+
+    fn on_frame_timing(&mut self, timing: FrameTiming) {
+        if let Some((_, render_loop)) = self.render_loop.get_mut() {
+            render_loop.on_frame_timing(timing);
+        }
+    }
+
+    fn on_dpi_scale_changed(&mut self, ctx: &mut Context, scale: f32) {
+        if let Some((_, render_loop)) = self.render_loop.get_mut() {
+            render_loop.on_dpi_scale_changed(ctx, scale);
+        }
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) {
+        if let Some((_, render_loop)) = self.render_loop.get_mut() {
+            render_loop.on_focus_changed(focused);
+        }
+    }
+
+    fn attach_to(&self, hwnd: HWND) -> bool {
+        let mut render_loop = self.render_loop.lock();
+        match &*render_loop {
+            Some((attached, _)) => *attached == hwnd.0,
+            None => {
+                *render_loop = (self.factory)(hwnd).map(|r| (hwnd.0, r));
+                render_loop.is_some()
+            }
+        }
+    }
+
+    unsafe fn render(&mut self, ui: &mut Ui) {
+        if let Some((_, render_loop)) = self.render_loop.get_mut() {
+            render_loop.render(ui);
+        }
+    }
+}
+
+// Builder options are kept here until `apply`, so a builder that is never applied changes nothing.
+#[derive(Default)]
+struct Settings {
+    input_policy: Option<InputPolicy>,
+    raw_input_capture: Option<bool>,
+    dpi_auto_scale: Option<bool>,
+    software_cursor: Option<bool>,
+    clipboard: Option<clipboard::ClipboardFactory>,
+    hotkeys: Vec<(Chord, HotkeyAction)>,
+    module: Option<HINSTANCE>,
+}
+
+impl Settings {
+    fn publish(&mut self) -> Vec<HotkeyId> {
+        if let Some(policy) = self.input_policy {
+            set_input_policy(policy);
+        }
+        if let Some(capture) = self.raw_input_capture {
+            set_raw_input_capture(capture);
+        }
+        if let Some(enabled) = self.dpi_auto_scale {
+            set_dpi_auto_scale(enabled);
+        }
+        if let Some(enabled) = self.software_cursor {
+            set_software_cursor(enabled);
+        }
+        if let Some(factory) = self.clipboard.take() {
+            clipboard::set_factory(factory);
+        }
+        if let Some(module) = self.module {
+            let _ = unsafe { MODULE.set(module) };
+        }
+
+        self.hotkeys
+            .drain(..)
+            .map(|(chord, action)| hotkeys::register(chord, action))
+            .collect()
+    }
+}
+
+pub struct Hudhook {
+    hooks: Vec<Box<dyn Hooks>>,
+    detours: Vec<Arc<SharedHook>>,
+    settings: Settings,
+    hotkeys: Vec<HotkeyId>,
+    initialized: bool,
+}
 unsafe impl Send for Hudhook {
 }
 unsafe impl Sync for Hudhook {
 }
 
 impl Hudhook {
-    pub fn builder() -> HudhookBuilder {
-        HudhookBuilder(Hudhook::new())
+    pub fn builder() -> Result<HudhookBuilder, HudhookError> {
+        Ok(HudhookBuilder(Hudhook::new()?))
     }
 
-    fn new() -> Self {
-        match unsafe { MH_Initialize() } {
-            MH_STATUS::MH_ERROR_ALREADY_INITIALIZED | MH_STATUS::MH_OK => {}
-            status @ MH_STATUS::MH_ERROR_MEMORY_ALLOC => panic!("MH_Initialize: {status:?}"),
-            _ => unreachable!(),
+    fn new() -> Result<Self, HudhookError> {
+        mh::initialize()?;
+
+        Ok(Hudhook {
+            hooks: Vec::new(),
+            detours: Vec::new(),
+            settings: Settings::default(),
+            hotkeys: Vec::new(),
+            initialized: true,
+        })
+    }
+
+    pub fn apply(mut self) -> Result<(), HudhookError> {
+        let mut hudhook = HUDHOOK.lock();
+        if hudhook.is_some() {
+            return Err(HudhookError::AlreadyApplied);
         }
 
-        Hudhook(Vec::new())
+        self.enable()?;
+        self.hotkeys = self.settings.publish();
+        *hudhook = Some(self);
+
+        Ok(())
+    }
+
+    pub fn enable(&self) -> Result<(), HudhookError> {
+        self.set_enabled(None, true)
     }
 
-    fn hooks(&self) -> impl IntoIterator<Item = &MhHook> {
-        self.0.iter().flat_map(|h| h.hooks())
+    pub fn disable(&self) -> Result<(), HudhookError> {
+        self.set_enabled(None, false)
     }
 
-    pub fn apply(self) -> Result<(), MH_STATUS> {
-        for hook in self.hooks() {
-            unsafe { hook.queue_enable()? };
+    pub fn enable_backend(&self, backend: Backend) -> Result<(), HudhookError> {
+        self.set_enabled(Some(backend), true)
+    }
+
+    pub fn disable_backend(&self, backend: Backend) -> Result<(), HudhookError> {
+        self.set_enabled(Some(backend), false)
+    }
+
+    // Only the MinHook state is toggled; pipelines, imgui contexts and textures stay alive.
+    fn set_enabled(&self, backend: Option<Backend>, enabled: bool) -> Result<(), HudhookError> {
+        let hooks = || {
+            self.hooks
+                .iter()
+                .filter(move |h| backend.is_none() || h.backend() == backend)
+        };
+
+        // Typed hooks don't belong to a backend, so they only follow the global toggle.
+        let detours = self
+            .detours
+            .iter()
+            .filter(|_| backend.is_none())
+            .map(|d| &d.hook);
+
+        for hook in hooks().flat_map(|h| h.hooks()).chain(detours) {
+            if enabled {
+                unsafe { hook.queue_enable()? };
+            } else {
+                unsafe { hook.queue_disable()? };
+            }
         }
 
         unsafe {
             let status = MH_ApplyQueued();
             if status != MH_STATUS::MH_OK {
-                return Err(status);
+                return Err(HudhookError::MinHook(status));
             }
         };
 
-        unsafe { HUDHOOK.set(self).ok() };
+        for h in hooks() {
+            h.set_enabled(enabled);
+        }
 
         Ok(())
     }
 
-    pub fn unapply(&mut self) -> Result<(), MH_STATUS> {
-        for hook in self.hooks() {
-            unsafe { hook.queue_disable()? };
+    pub fn unapply(&mut self) -> Result<(), HudhookError> {
+        self.unapply_with_timeout(DRAIN_TIMEOUT)
+    }
+
+    pub fn unapply_with_timeout(&mut self, timeout: Duration) -> Result<(), HudhookError> {
+        self.disable()?;
+        for id in self.hotkeys.drain(..) {
+            hotkeys::unregister(id);
         }
 
-        unsafe {
-            let status = MH_ApplyQueued();
-            if status != MH_STATUS::MH_OK {
-                return Err(status);
-            }
-        };
+        let subclassed = self
+            .hooks
+            .iter()
+            .map(|h| h.restore_wnd_procs())
+            .sum::<usize>();
+        self.wait_for_drain(timeout)?;
+
+        // Only our own hooks are removed, so other instances in this module keep working. Typed
+        // hooks stay disabled until their last `TypedHook` handle is dropped.
+        for mh_hook in self.hooks.iter().flat_map(|h| h.hooks()) {
+            unsafe { mh_hook.remove()? };
+        }
+        self.detours.clear();
+        for mut hook in self.hooks.drain(..) {
+            unsafe { hook.unhook() };
+        }
 
-        unsafe {
-            let status = MH_Uninitialize();
-            if status != MH_STATUS::MH_OK {
-                return Err(status);
-            }
-        };
+        if std::mem::take(&mut self.initialized) {
+            mh::uninitialize()?;
+        }
 
-        for hook in &mut self.0 {
-            unsafe { hook.unhook() };
+        // Our window procedures stay in those chains, so the module must not be unloaded.
+        if subclassed > 0 {
+            return Err(HudhookError::WndProcSubclassed(subclassed));
         }
 
         Ok(())
     }
+
+    fn in_flight(&self) -> usize {
+        self.hooks.iter().map(|h| h.in_flight()).sum::<usize>()
+            + self
+                .detours
+                .iter()
+                .map(|d| d.in_flight.count())
+                .sum::<usize>()
+            + renderer::WND_PROC_IN_FLIGHT.count()
+    }
+
+    fn wait_for_drain(&self, timeout: Duration) -> Result<(), HudhookError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let in_flight = self.in_flight();
+            if in_flight == 0 {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(HudhookError::DrainTimeout(in_flight));
+            }
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+    }
+}
+
+// A builder that fails or is thrown away still has to give back its hooks and MinHook reference.
+impl Drop for Hudhook {
+    fn drop(&mut self) {
+        if self.initialized {
+            let _ = self.unapply();
+        }
+    }
 }
 
 pub struct HudhookBuilder(Hudhook);
@@ -201,12 +550,119 @@ impl HudhookBuilder {
         mut self,
         render_loop: impl ImguiRenderLoop + Send + Sync + 'static,
     ) -> Self {
-        self.0 .0.push(T::from_render_loop(render_loop));
+        self.0.hooks.push(T::from_render_loop(render_loop));
+        self
+    }
+
+    pub fn try_with<T, R>(&mut self, render_loop: R) -> Result<(), (HudhookError, R)>
+    where
+        T: Hooks + 'static,
+        R: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        self.0.hooks.push(T::try_from_render_loop(render_loop)?);
+        Ok(())
+    }
+
+    pub fn with_auto<R>(
+        &mut self,
+        render_loop: R,
+        wait: Option<Duration>,
+    ) -> Result<Backend, (HudhookError, R)>
+    where
+        R: ImguiRenderLoop + Send + Sync + 'static,
+    {
+        let backends = match wait {
+            Some(timeout) => hooks::detect::wait_for(timeout),
+            None => hooks::detect::detect(),
+        };
+        let backends = match hooks::detect::retain_presenting(
+            backends,
+            wait.unwrap_or(hooks::detect::PRESENT_PROBE_TIMEOUT),
+        ) {
+            Ok(backends) => backends,
+            Err(e) => return Err((e, render_loop)),
+        };
+
+        let mut render_loop = render_loop;
+        let mut error = HudhookError::NoBackendDetected;
+
+        for backend in backends {
+            let result = match backend {
+                #[cfg(feature = "dx9")]
+                Backend::Dx9 => self.try_with::<hooks::dx9::ImguiDx9Hooks, R>(render_loop),
+                #[cfg(feature = "dx11")]
+                Backend::Dx11 => self.try_with::<hooks::dx11::ImguiDx11Hooks, R>(render_loop),
+                #[cfg(feature = "dx12")]
+                Backend::Dx12 => self.try_with::<hooks::dx12::ImguiDx12Hooks, R>(render_loop),
+                #[cfg(feature = "opengl3")]
+                Backend::OpenGl3 => {
+                    self.try_with::<hooks::opengl3::ImguiOpenGl3Hooks, R>(render_loop)
+                }
+                #[allow(unreachable_patterns)]
+                _ => continue,
+            };
+
+            match result {
+                Ok(()) => return Ok(backend),
+                Err((e, r)) => {
+                    error = e;
+                    render_loop = r;
+                }
+            }
+        }
+
+        Err((error, render_loop))
+    }
+
+    pub fn with_factory<T, F>(mut self, factory: F) -> Self
+    where
+        T: Hooks + 'static,
+        F: Fn(HWND) -> Option<Box<dyn ImguiRenderLoop + Send + Sync>> + Send + Sync + 'static,
+    {
+        self.0.hooks.push(T::from_render_loop_factory(factory));
+        self
+    }
+
+    pub fn with_hook<F: HookFn>(mut self, hook: &TypedHook<F>) -> Self {
+        self.0.detours.push(hook.shared());
+        self
+    }
+
+    pub fn with_input_policy(mut self, policy: InputPolicy) -> Self {
+        self.0.settings.input_policy = Some(policy);
+        self
+    }
+
+    pub fn with_raw_input_capture(mut self, capture: bool) -> Self {
+        self.0.settings.raw_input_capture = Some(capture);
+        self
+    }
+
+    pub fn with_dpi_auto_scale(mut self, enabled: bool) -> Self {
+        self.0.settings.dpi_auto_scale = Some(enabled);
         self
     }
 
-    pub fn with_hmodule(self, module: HINSTANCE) -> Self {
-        unsafe { MODULE.set(module).unwrap() };
+    pub fn with_software_cursor(mut self, enabled: bool) -> Self {
+        self.0.settings.software_cursor = Some(enabled);
+        self
+    }
+
+    pub fn with_clipboard_backend<C>(mut self, backend: C) -> Self
+    where
+        C: ClipboardBackend + Clone + Send + Sync,
+    {
+        self.0.settings.clipboard = Some(clipboard::factory(backend));
+        self
+    }
+
+    pub fn with_hotkey(mut self, chord: Chord, action: HotkeyAction) -> Self {
+        self.0.settings.hotkeys.push((chord, action));
+        self
+    }
+
+    pub fn with_hmodule(mut self, module: HINSTANCE) -> Self {
+        self.0.settings.module = Some(module);
         self
     }
 
@@ -214,3 +670,80 @@ impl HudhookBuilder {
         self.0
     }
 }
+
+#[doc(hidden)]
+pub unsafe fn dll_attach<F>(hmodule: HINSTANCE, console: bool, install: F)
+where
+    F: FnOnce(&mut HudhookBuilder) -> Result<(), HudhookError> + Send + 'static,
+{
+    let _ = DisableThreadLibraryCalls(hmodule);
+
+    // Nothing here may run under the loader lock.
+    thread::spawn(move || {
+        if console && alloc_console().is_ok() {
+            enable_console_colors();
+        }
+
+        let result = Hudhook::builder().and_then(|builder| {
+            let mut builder = builder.with_hmodule(hmodule);
+            install(&mut builder)?;
+            builder.build().apply()
+        });
+        // Reported through `log`, so it reaches whatever logger the DLL installs, console or not.
+        match result {
+            Ok(()) => log::info!("hooks applied"),
+            Err(e) => log::error!("couldn't apply hooks: {e}"),
+        }
+    });
+}
+
+#[doc(hidden)]
+pub fn dll_attach_auto<R>(builder: &mut HudhookBuilder, render_loop: R) -> Result<(), HudhookError>
+where
+    R: ImguiRenderLoop + Send + Sync + 'static,
+{
+    match builder.with_auto(render_loop, Some(DLL_MAIN_AUTO_WAIT)) {
+        Ok(backend) => {
+            log::info!("using {backend:?} backend");
+            Ok(())
+        }
+        Err((e, _)) => Err(e),
+    }
+}
+
+#[macro_export]
+macro_rules! hudhook {
+    (@dll_main $console:literal, $install:expr) => {
+        #[no_mangle]
+        pub unsafe extern "system" fn DllMain(
+            hmodule: $crate::windows::Win32::Foundation::HINSTANCE,
+            reason: u32,
+            _: *mut ::std::ffi::c_void,
+        ) -> $crate::windows::Win32::Foundation::BOOL {
+            if reason == $crate::windows::Win32::System::SystemServices::DLL_PROCESS_ATTACH {
+                $crate::dll_attach(hmodule, $console, $install);
+            }
+            $crate::windows::Win32::Foundation::BOOL(1)
+        }
+    };
+    (auto, $render_loop:expr) => {
+        $crate::hudhook!(@dll_main false, move |builder: &mut $crate::HudhookBuilder| {
+            $crate::dll_attach_auto(builder, $render_loop)
+        });
+    };
+    (auto, $render_loop:expr, console) => {
+        $crate::hudhook!(@dll_main true, move |builder: &mut $crate::HudhookBuilder| {
+            $crate::dll_attach_auto(builder, $render_loop)
+        });
+    };
+    ($hooks:ty, $render_loop:expr) => {
+        $crate::hudhook!(@dll_main false, move |builder: &mut $crate::HudhookBuilder| {
+            builder.try_with::<$hooks, _>($render_loop).map_err(|(e, _)| e)
+        });
+    };
+    ($hooks:ty, $render_loop:expr, console) => {
+        $crate::hudhook!(@dll_main true, move |builder: &mut $crate::HudhookBuilder| {
+            builder.try_with::<$hooks, _>($render_loop).map_err(|(e, _)| e)
+        });
+    };
+}
@@ -1,20 +1,138 @@
-use std::{ffi::c_void, ptr::null_mut};
+use std::{
+    ffi::{c_void, CString},
+    fmt,
+    marker::PhantomData,
+    ptr::null_mut,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
-use minhook_raw::sys::{MH_CreateHook, MH_QueueDisableHook, MH_QueueEnableHook, MH_STATUS};
+use minhook_raw::sys::{
+    MH_CreateHook,
+    MH_Initialize,
+    MH_QueueDisableHook,
+    MH_QueueEnableHook,
+    MH_RemoveHook,
+    MH_Uninitialize,
+    MH_STATUS,
+};
+use parking_lot::Mutex;
+use windows::{
+    core::{HSTRING, PCSTR},
+    Win32::{
+        Foundation::HMODULE,
+        System::LibraryLoader::{GetModuleHandleW, GetProcAddress},
+    },
+};
 
+#[cfg(windows)]
+use crate::scan::{self, Pattern, RipRelative};
+use crate::{
+    error::{HudhookError, Result},
+    util::InFlight,
+};
+
+const MODULE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Each module links its own copy of MinHook, so this only counts the Hudhook instances of this
+// module; unapplying one of them doesn't uninitialize MinHook underneath the others.
+static REFCOUNT: Mutex<usize> = parking_lot::const_mutex(0);
+
+pub(crate) fn initialize() -> Result<()> {
+    let mut refcount = REFCOUNT.lock();
+
+    if *refcount == 0 {
+        match unsafe { MH_Initialize() } {
+            MH_STATUS::MH_OK | MH_STATUS::MH_ERROR_ALREADY_INITIALIZED => {}
+            status => return Err(HudhookError::MinHook(status)),
+        }
+    }
+
+    *refcount += 1;
+    Ok(())
+}
+
+pub(crate) fn uninitialize() -> Result<()> {
+    let mut refcount = REFCOUNT.lock();
+
+    if *refcount == 1 {
+        let status = unsafe { MH_Uninitialize() };
+        if status != MH_STATUS::MH_OK {
+            return Err(HudhookError::MinHook(status));
+        }
+    }
+
+    *refcount = refcount.saturating_sub(1);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Export<'a> {
+    Name(&'a str),
+    Ordinal(u16),
+}
+
+impl fmt::Display for Export<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Export::Name(name) => f.write_str(name),
+            Export::Ordinal(ordinal) => write!(f, "#{ordinal}"),
+        }
+    }
+}
+
+fn wait_for_module(module: &str, wait: Option<Duration>) -> Result<HMODULE> {
+    let name = HSTRING::from(module);
+    let deadline = wait.map(|wait| Instant::now() + wait);
+
+    loop {
+        if let Ok(handle) = unsafe { GetModuleHandleW(&name) } {
+            return Ok(handle);
+        }
+        if deadline.is_none_or(|deadline| Instant::now() >= deadline) {
+            return Err(HudhookError::ModuleNotLoaded(module.to_string()));
+        }
+        thread::sleep(MODULE_POLL_INTERVAL);
+    }
+}
+
+pub fn find_export(module: &str, export: Export, wait: Option<Duration>) -> Result<*mut c_void> {
+    let handle = wait_for_module(module, wait)?;
+
+    let not_found = || HudhookError::ExportNotFound {
+        module: module.to_string(),
+        export: export.to_string(),
+    };
+
+    let addr = match export {
+        Export::Name(name) => {
+            let name = CString::new(name).map_err(|_| not_found())?;
+            unsafe { GetProcAddress(handle, PCSTR(name.as_ptr() as *const u8)) }
+        }
+        // An ordinal is passed in the low word of the name pointer.
+        Export::Ordinal(ordinal) => unsafe {
+            GetProcAddress(handle, PCSTR(ordinal as usize as *const u8))
+        },
+    };
+
+    addr.map(|f| f as *mut c_void).ok_or_else(not_found)
+}
+
+#[derive(Clone)]
 pub struct MhHook {
     addr: *mut c_void,
     trampoline: *mut c_void,
 }
 
 impl MhHook {
-    pub unsafe fn new(addr: *mut c_void, hook_impl: *mut c_void) -> Result<Self, MH_STATUS> {
+    pub unsafe fn new(addr: *mut c_void, hook_impl: *mut c_void) -> Result<Self> {
         let mut trampoline = null_mut();
 
         let status = MH_CreateHook(addr, hook_impl, &mut trampoline);
 
         if status != MH_STATUS::MH_OK {
-            return Err(status);
+            return Err(HudhookError::MinHook(status));
         }
 
         Ok(Self {
@@ -23,27 +141,269 @@ impl MhHook {
         })
     }
 
+    // Hooks that share a target with another backend can fail halfway through, so the ones already
+    // created are removed again rather than left registered.
+    pub(crate) unsafe fn new_all<const N: usize>(
+        targets: [(*mut c_void, *mut c_void); N],
+    ) -> Result<[Self; N]> {
+        let mut hooks = Vec::with_capacity(N);
+        for (addr, hook_impl) in targets {
+            match Self::new(addr, hook_impl) {
+                Ok(hook) => hooks.push(hook),
+                Err(e) => {
+                    for hook in &hooks {
+                        let _ = hook.remove();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(hooks.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+
+    pub unsafe fn from_export(
+        module: &str,
+        name: &str,
+        hook_impl: *mut c_void,
+        wait: Option<Duration>,
+    ) -> Result<Self> {
+        Self::new(find_export(module, Export::Name(name), wait)?, hook_impl)
+    }
+
+    pub unsafe fn from_ordinal(
+        module: &str,
+        ordinal: u16,
+        hook_impl: *mut c_void,
+        wait: Option<Duration>,
+    ) -> Result<Self> {
+        Self::new(
+            find_export(module, Export::Ordinal(ordinal), wait)?,
+            hook_impl,
+        )
+    }
+
+    #[cfg(windows)]
+    pub unsafe fn from_pattern(
+        module: &str,
+        pattern: &Pattern,
+        rip: Option<RipRelative>,
+        hook_impl: *mut c_void,
+    ) -> Result<Self> {
+        Self::new(scan::find_unique(module, pattern, rip)?, hook_impl)
+    }
+
     pub fn trampoline(&self) -> *mut c_void {
         self.trampoline
     }
 
-    pub unsafe fn queue_enable(&self) -> Result<(), MH_STATUS> {
+    pub unsafe fn queue_enable(&self) -> Result<()> {
         let status = MH_QueueEnableHook(self.addr);
 
         if status == MH_STATUS::MH_OK {
             Ok(())
         } else {
-            Err(status)
+            Err(HudhookError::MinHook(status))
         }
     }
 
-    pub unsafe fn queue_disable(&self) -> Result<(), MH_STATUS> {
+    pub unsafe fn queue_disable(&self) -> Result<()> {
         let status = MH_QueueDisableHook(self.addr);
 
         if status == MH_STATUS::MH_OK {
             Ok(())
         } else {
-            Err(status)
+            Err(HudhookError::MinHook(status))
         }
     }
+
+    pub unsafe fn remove(&self) -> Result<()> {
+        let status = MH_RemoveHook(self.addr);
+
+        if status == MH_STATUS::MH_OK {
+            Ok(())
+        } else {
+            Err(HudhookError::MinHook(status))
+        }
+    }
+}
+
+// Implemented for `extern "system"` and `extern "C"` function pointers. Only the safe ones can be
+// called through `TypedHook::call_original`; unsafe ones go through `TypedHook::original`.
+pub unsafe trait HookFn: Copy + Send + Sync + 'static {
+    type Args;
+    type Output;
+
+    fn addr(self) -> *mut c_void;
+
+    unsafe fn from_addr(addr: *mut c_void) -> Self;
+}
+
+pub trait CallFn: HookFn {
+    fn call(self, args: Self::Args) -> Self::Output;
+}
+
+macro_rules! impl_hook_fn {
+    ($($arg:ident),*) => {
+        impl_hook_fn!(@abi "system", $($arg),*);
+        impl_hook_fn!(@abi "C", $($arg),*);
+    };
+    (@abi $abi:literal, $($arg:ident),*) => {
+        unsafe impl<R: 'static, $($arg: 'static),*> HookFn for extern $abi fn($($arg),*) -> R {
+            type Args = ($($arg,)*);
+            type Output = R;
+
+            fn addr(self) -> *mut c_void {
+                self as *mut c_void
+            }
+
+            unsafe fn from_addr(addr: *mut c_void) -> Self {
+                std::mem::transmute::<*mut c_void, Self>(addr)
+            }
+        }
+
+        impl<R: 'static, $($arg: 'static),*> CallFn for extern $abi fn($($arg),*) -> R {
+            #[allow(non_snake_case)]
+            fn call(self, ($($arg,)*): Self::Args) -> R {
+                self($($arg),*)
+            }
+        }
+
+        unsafe impl<R: 'static, $($arg: 'static),*> HookFn
+            for unsafe extern $abi fn($($arg),*) -> R
+        {
+            type Args = ($($arg,)*);
+            type Output = R;
+
+            fn addr(self) -> *mut c_void {
+                self as *mut c_void
+            }
+
+            unsafe fn from_addr(addr: *mut c_void) -> Self {
+                std::mem::transmute::<*mut c_void, Self>(addr)
+            }
+        }
+    };
+}
+
+impl_hook_fn!();
+impl_hook_fn!(A);
+impl_hook_fn!(A, B);
+impl_hook_fn!(A, B, C);
+impl_hook_fn!(A, B, C, D);
+impl_hook_fn!(A, B, C, D, E);
+impl_hook_fn!(A, B, C, D, E, F);
+impl_hook_fn!(A, B, C, D, E, F, G);
+impl_hook_fn!(A, B, C, D, E, F, G, H);
+impl_hook_fn!(A, B, C, D, E, F, G, H, I);
+impl_hook_fn!(A, B, C, D, E, F, G, H, I, J);
+impl_hook_fn!(A, B, C, D, E, F, G, H, I, J, K);
+impl_hook_fn!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+// Every `TypedHook` handle and every `Hudhook` it was added to share this, so the trampoline and
+// the MinHook reference it needs are only released once the last of them is gone.
+pub(crate) struct SharedHook {
+    pub(crate) hook: MhHook,
+    pub(crate) in_flight: InFlight,
+}
+
+unsafe impl Send for SharedHook {}
+unsafe impl Sync for SharedHook {}
+
+impl Drop for SharedHook {
+    fn drop(&mut self) {
+        let _ = unsafe { self.hook.remove() };
+        let _ = uninitialize();
+    }
+}
+
+pub struct TypedHook<F: HookFn> {
+    shared: Arc<SharedHook>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: HookFn> Clone for TypedHook<F> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+            _marker: PhantomData,
+        }
+    }
+}
+
+unsafe impl<F: HookFn> Send for TypedHook<F> {}
+unsafe impl<F: HookFn> Sync for TypedHook<F> {}
+
+impl<F: HookFn> TypedHook<F> {
+    pub unsafe fn new(target: F, detour: F) -> Result<Self> {
+        initialize()?;
+
+        let hook = match MhHook::new(target.addr(), detour.addr()) {
+            Ok(hook) => hook,
+            Err(e) => {
+                let _ = uninitialize();
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            shared: Arc::new(SharedHook {
+                hook,
+                in_flight: InFlight::new(),
+            }),
+            _marker: PhantomData,
+        })
+    }
+    pub unsafe fn from_addr(target: *mut c_void, detour: F) -> Result<Self> {
+        Self::new(F::from_addr(target), detour)
+    }
+
+    pub unsafe fn from_export(
+        module: &str,
+        name: &str,
+        detour: F,
+        wait: Option<Duration>,
+    ) -> Result<Self> {
+        Self::from_addr(find_export(module, Export::Name(name), wait)?, detour)
+    }
+
+    pub unsafe fn from_ordinal(
+        module: &str,
+        ordinal: u16,
+        detour: F,
+        wait: Option<Duration>,
+    ) -> Result<Self> {
+        Self::from_addr(find_export(module, Export::Ordinal(ordinal), wait)?, detour)
+    }
+
+    #[cfg(windows)]
+    pub unsafe fn from_pattern(
+        module: &str,
+        pattern: &Pattern,
+        rip: Option<RipRelative>,
+        detour: F,
+    ) -> Result<Self> {
+        Self::from_addr(scan::find_unique(module, pattern, rip)?, detour)
+    }
+
+    // The pointer stays valid only while a handle to this hook is alive, and calls through it are
+    // not counted when `Hudhook::unapply` waits for detours to return.
+    pub unsafe fn original(&self) -> F {
+        F::from_addr(self.shared.hook.trampoline())
+    }
+
+    pub fn hook(&self) -> &MhHook {
+        &self.shared.hook
+    }
+
+    pub(crate) fn shared(&self) -> Arc<SharedHook> {
+        Arc::clone(&self.shared)
+    }
+}
+
+impl<F: CallFn> TypedHook<F> {
+    pub fn call_original(&self, args: F::Args) -> F::Output {
+        let _in_flight = self.shared.in_flight.enter();
+        unsafe { self.original() }.call(args)
+    }
 }
@@ -11,7 +11,7 @@ use imgui::{
     TextureId,
 };
 use windows::{
-    core::{s, Error, Result, HRESULT},
+    core::s,
     Win32::{
         Foundation::RECT,
         Graphics::{
@@ -22,7 +22,12 @@ use windows::{
     },
 };
 
-use crate::{renderer::RenderEngine, util, RenderContext};
+use crate::{
+    error::{HudhookError, Result},
+    renderer::RenderEngine,
+    util,
+    RenderContext,
+};
 
 pub struct D3D11RenderEngine {
     device: ID3D11Device,
@@ -30,6 +35,7 @@ pub struct D3D11RenderEngine {
 
     shader_program: ShaderProgram,
     texture_heap: TextureHeap,
+    font_texture: Option<TextureId>,
 
     vertex_buffer: Buffer<DrawVert>,
     index_buffer: Buffer<DrawIdx>,
@@ -60,6 +66,7 @@ impl D3D11RenderEngine {
             device_context,
             shader_program,
             texture_heap,
+            font_texture: None,
             vertex_buffer,
             index_buffer,
             projection_buffer,
@@ -114,11 +121,23 @@ impl RenderEngine for D3D11RenderEngine {
     fn setup_fonts(&mut self, ctx: &mut Context) -> Result<()> {
         let fonts = ctx.fonts();
         let fonts_texture = fonts.build_rgba32_texture();
-        fonts.tex_id = self.load_texture(
-            fonts_texture.data,
-            fonts_texture.width,
-            fonts_texture.height,
-        )?;
+        fonts.tex_id = match self.font_texture {
+            Some(texture_id) => unsafe {
+                self.texture_heap.recreate_texture(
+                    texture_id,
+                    fonts_texture.data,
+                    fonts_texture.width,
+                    fonts_texture.height,
+                )?;
+                texture_id
+            },
+            None => self.load_texture(
+                fonts_texture.data,
+                fonts_texture.width,
+                fonts_texture.height,
+            )?,
+        };
+        self.font_texture = Some(fonts.tex_id);
         Ok(())
     }
 }
@@ -550,6 +569,7 @@ impl<T> Buffer<T> {
                 Some(v),
             )
         })
+        .map_err(HudhookError::from)
     }
 
     fn clear(&mut self) {
@@ -625,6 +645,36 @@ impl TextureHeap {
     }
 
     unsafe fn create_texture(&mut self, data: &[u8], width: u32, height: u32) -> Result<TextureId> {
+        let id = TextureId::from(self.textures.len());
+        let texture = self.new_texture(id, data, width, height)?;
+        self.textures.push(texture);
+
+        Ok(id)
+    }
+
+    unsafe fn recreate_texture(
+        &mut self,
+        texture_id: TextureId,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        if texture_id.id() >= self.textures.len() {
+            return Err(HudhookError::TextureNotFound(texture_id));
+        }
+
+        self.textures[texture_id.id()] = self.new_texture(texture_id, data, width, height)?;
+
+        Ok(())
+    }
+
+    unsafe fn new_texture(
+        &self,
+        id: TextureId,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Texture> {
         let resource: ID3D11Texture2D = util::try_out_ptr(|v| {
             self.device.CreateTexture2D(
                 &D3D11_TEXTURE2D_DESC {
@@ -668,16 +718,13 @@ impl TextureHeap {
             )
         })?;
 
-        let id = TextureId::from(self.textures.len());
-        self.textures.push(Texture {
+        Ok(Texture {
             resource,
             shader_resource_view,
             id,
             width,
             height,
-        });
-
-        Ok(id)
+        })
     }
 
     unsafe fn update_texture(
@@ -687,9 +734,14 @@ impl TextureHeap {
         width: u32,
         height: u32,
     ) -> Result<()> {
-        let texture = &mut self.textures[texture_id.id()];
+        let Some(texture) = self.textures.get_mut(texture_id.id()) else {
+            return Err(HudhookError::TextureNotFound(texture_id));
+        };
         if texture.width != width || texture.height != height {
-            return Err(Error::from_hresult(HRESULT(-1)));
+            return Err(HudhookError::TextureSizeMismatch {
+                expected: (texture.width, texture.height),
+                actual: (width, height),
+            });
         }
 
         self.device_context.UpdateSubresource(
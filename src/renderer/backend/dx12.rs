@@ -17,7 +17,7 @@ use imgui::{
     TextureId,
 };
 use windows::{
-    core::{s, w, Error, Interface, Result, HRESULT},
+    core::{s, w, Interface},
     Win32::{
         Foundation::*,
         Graphics::{
@@ -29,6 +29,7 @@ use windows::{
 };
 
 use crate::{
+    error::{HudhookError, Result},
     renderer::RenderEngine,
     util::{self, Fence},
     RenderContext,
@@ -45,6 +46,7 @@ pub struct D3D12RenderEngine {
     rtv_heap: ID3D12DescriptorHeap,
     rtv_heap_start: D3D12_CPU_DESCRIPTOR_HANDLE,
     texture_heap: TextureHeap,
+    font_texture: Option<TextureId>,
 
     root_signature: ID3D12RootSignature,
     pipeline_state: ID3D12PipelineState,
@@ -86,6 +88,7 @@ impl D3D12RenderEngine {
             rtv_heap,
             rtv_heap_start,
             texture_heap,
+            font_texture: None,
             root_signature,
             pipeline_state,
             vertex_buffer,
@@ -174,11 +177,28 @@ impl RenderEngine for D3D12RenderEngine {
     fn setup_fonts(&mut self, ctx: &mut Context) -> Result<()> {
         let fonts = ctx.fonts();
         let fonts_texture = fonts.build_rgba32_texture();
-        fonts.tex_id = self.load_texture(
-            fonts_texture.data,
-            fonts_texture.width,
-            fonts_texture.height,
-        )?;
+        fonts.tex_id = match self.font_texture {
+            Some(texture_id) => unsafe {
+                self.texture_heap.recreate_texture(
+                    texture_id,
+                    fonts_texture.width,
+                    fonts_texture.height,
+                )?;
+                self.texture_heap.upload_texture(
+                    texture_id,
+                    fonts_texture.data,
+                    fonts_texture.width,
+                    fonts_texture.height,
+                )?;
+                texture_id
+            },
+            None => self.load_texture(
+                fonts_texture.data,
+                fonts_texture.width,
+                fonts_texture.height,
+            )?,
+        };
+        self.font_texture = Some(fonts.tex_id);
         Ok(())
     }
 }
@@ -669,6 +689,7 @@ impl<T> Buffer<T> {
                 v,
             )
         })
+        .map_err(HudhookError::from)
     }
 
     fn clear(&mut self) {
@@ -779,13 +800,38 @@ impl TextureHeap {
     unsafe fn create_texture(&mut self, width: u32, height: u32) -> Result<TextureId> {
         self.resize_heap()?;
 
+        let id = TextureId::from(self.textures.len());
+        let texture = self.new_texture(id, width, height)?;
+        self.textures.push(texture);
+
+        Ok(id)
+    }
+
+    // The render engine waits for the GPU after every frame, so the old resource is no longer in
+    // use when it is dropped here.
+    unsafe fn recreate_texture(
+        &mut self,
+        texture_id: TextureId,
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        if texture_id.id() >= self.textures.len() {
+            return Err(HudhookError::TextureNotFound(texture_id));
+        }
+
+        self.textures[texture_id.id()] = self.new_texture(texture_id, width, height)?;
+
+        Ok(())
+    }
+
+    unsafe fn new_texture(&self, id: TextureId, width: u32, height: u32) -> Result<Texture> {
         let cpu_heap_start = self.srv_heap.GetCPUDescriptorHandleForHeapStart();
         let gpu_heap_start = self.srv_heap.GetGPUDescriptorHandleForHeapStart();
         let heap_inc_size = self
             .device
             .GetDescriptorHandleIncrementSize(D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV);
 
-        let texture_index = self.textures.len() as u32;
+        let texture_index = id.id() as u32;
 
         let cpu_desc = D3D12_CPU_DESCRIPTOR_HANDLE {
             ptr: cpu_heap_start.ptr + (texture_index * heap_inc_size) as usize,
@@ -844,15 +890,12 @@ impl TextureHeap {
             cpu_desc,
         );
 
-        let id = TextureId::from(self.textures.len());
-        self.textures.push(Texture {
-            resource: texture.clone(),
+        Ok(Texture {
+            resource: texture,
             gpu_desc,
             width,
             height,
-        });
-
-        Ok(id)
+        })
     }
 
     unsafe fn upload_texture(
@@ -862,9 +905,14 @@ impl TextureHeap {
         width: u32,
         height: u32,
     ) -> Result<()> {
-        let texture = &self.textures[texture_id.id()];
+        let Some(texture) = self.textures.get(texture_id.id()) else {
+            return Err(HudhookError::TextureNotFound(texture_id));
+        };
         if texture.width != width || texture.height != height {
-            return Err(Error::from_hresult(HRESULT(-1)));
+            return Err(HudhookError::TextureSizeMismatch {
+                expected: (texture.width, texture.height),
+                actual: (width, height),
+            });
         }
 
         let upload_row_size = width * 4;
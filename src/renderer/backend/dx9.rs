@@ -11,12 +11,16 @@ use imgui::{
     TextureId,
 };
 use windows::{
-    core::{Error, Result, HRESULT},
     Foundation::Numerics::Matrix4x4,
     Win32::{Foundation::RECT, Graphics::Direct3D9::*},
 };
 
-use crate::{renderer::RenderEngine, util, RenderContext};
+use crate::{
+    error::{HudhookError, Result},
+    renderer::RenderEngine,
+    util,
+    RenderContext,
+};
 
 const D3DFVF_CUSTOMVERTEX: u32 = D3DFVF_XYZ | D3DFVF_DIFFUSE | D3DFVF_TEX1;
 const MAT_IDENTITY: Matrix4x4 = Matrix4x4 {
@@ -49,6 +53,7 @@ pub struct D3D9RenderEngine {
     device: IDirect3DDevice9,
 
     texture_heap: TextureHeap,
+    font_texture: Option<TextureId>,
 
     vertex_buffer: Buffer<IDirect3DVertexBuffer9, CustomVertex>,
     index_buffer: Buffer<IDirect3DIndexBuffer9, DrawIdx>,
@@ -75,6 +80,7 @@ impl D3D9RenderEngine {
         Ok(Self {
             device,
             texture_heap,
+            font_texture: None,
             vertex_buffer,
             index_buffer,
             projection_buffer,
@@ -126,11 +132,28 @@ impl RenderEngine for D3D9RenderEngine {
     fn setup_fonts(&mut self, ctx: &mut Context) -> Result<()> {
         let fonts = ctx.fonts();
         let fonts_texture = fonts.build_rgba32_texture();
-        fonts.tex_id = self.load_texture(
-            fonts_texture.data,
-            fonts_texture.width,
-            fonts_texture.height,
-        )?;
+        fonts.tex_id = match self.font_texture {
+            Some(texture_id) => unsafe {
+                self.texture_heap.recreate_texture(
+                    texture_id,
+                    fonts_texture.width,
+                    fonts_texture.height,
+                )?;
+                self.texture_heap.upload_texture(
+                    texture_id,
+                    fonts_texture.data,
+                    fonts_texture.width,
+                    fonts_texture.height,
+                )?;
+                texture_id
+            },
+            None => self.load_texture(
+                fonts_texture.data,
+                fonts_texture.width,
+                fonts_texture.height,
+            )?,
+        };
+        self.font_texture = Some(fonts.tex_id);
         Ok(())
     }
 }
@@ -372,6 +395,7 @@ impl BufferType for IDirect3DVertexBuffer9 {
                 ptr::null_mut(),
             )
         })
+        .map_err(HudhookError::from)
     }
 
     fn upload<T>(&mut self, data: &[T]) -> Result<()> {
@@ -413,6 +437,7 @@ impl BufferType for IDirect3DIndexBuffer9 {
                 ptr::null_mut(),
             )
         })
+        .map_err(HudhookError::from)
     }
 
     fn upload<T>(&mut self, data: &[T]) -> Result<()> {
@@ -462,6 +487,29 @@ impl TextureHeap {
     }
 
     unsafe fn create_texture(&mut self, width: u32, height: u32) -> Result<TextureId> {
+        let id = TextureId::from(self.textures.len());
+        let texture = self.new_texture(id, width, height)?;
+        self.textures.push(texture);
+
+        Ok(id)
+    }
+
+    unsafe fn recreate_texture(
+        &mut self,
+        texture_id: TextureId,
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        if texture_id.id() >= self.textures.len() {
+            return Err(HudhookError::TextureNotFound(texture_id));
+        }
+
+        self.textures[texture_id.id()] = self.new_texture(texture_id, width, height)?;
+
+        Ok(())
+    }
+
+    unsafe fn new_texture(&self, id: TextureId, width: u32, height: u32) -> Result<Texture> {
         let resource = util::try_out_ptr(|v| {
             self.device.CreateTexture(
                 width,
@@ -475,15 +523,12 @@ impl TextureHeap {
             )
         })?;
 
-        let id = TextureId::from(self.textures.len());
-        self.textures.push(Texture {
+        Ok(Texture {
             resource,
             id,
             width,
             height,
-        });
-
-        Ok(id)
+        })
     }
 
     unsafe fn upload_texture(
@@ -493,9 +538,14 @@ impl TextureHeap {
         width: u32,
         height: u32,
     ) -> Result<()> {
-        let texture = &self.textures[texture_id.id()];
+        let Some(texture) = self.textures.get(texture_id.id()) else {
+            return Err(HudhookError::TextureNotFound(texture_id));
+        };
         if texture.width != width || texture.height != height {
-            return Err(Error::from_hresult(HRESULT(-1)));
+            return Err(HudhookError::TextureSizeMismatch {
+                expected: (texture.width, texture.height),
+                actual: (width, height),
+            });
         }
 
         let mut r: D3DLOCKED_RECT = Default::default();
@@ -556,7 +606,7 @@ impl StateBackup {
                     surface,
                 })
             }
-            Err(e) => Err(e),
+            Err(e) => Err(e.into()),
         }
     }
 
@@ -0,0 +1,117 @@
+use imgui::{Context, DrawData, TextureId};
+
+use crate::{
+    error::{HudhookError, Result},
+    renderer::RenderEngine,
+    RenderContext,
+};
+
+#[derive(Debug, Default)]
+pub struct MockRenderEngine {
+    textures: Vec<(u32, u32)>,
+    font_texture: Option<TextureId>,
+    frames_rendered: u32,
+    draw_list_count: usize,
+    vertex_count: usize,
+    index_count: usize,
+}
+
+impl MockRenderEngine {
+    pub fn frames_rendered(&self) -> u32 {
+        self.frames_rendered
+    }
+
+    pub fn draw_list_count(&self) -> usize {
+        self.draw_list_count
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+
+    pub fn texture_size(&self, texture_id: TextureId) -> Option<(u32, u32)> {
+        self.textures.get(texture_id.id()).copied()
+    }
+}
+
+impl RenderContext for MockRenderEngine {
+    fn load_texture(&mut self, data: &[u8], width: u32, height: u32) -> Result<TextureId> {
+        check_data_length(data, width, height)?;
+
+        self.textures.push((width, height));
+        Ok(TextureId::new(self.textures.len() - 1))
+    }
+
+    fn replace_texture(
+        &mut self,
+        texture_id: TextureId,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        check_data_length(data, width, height)?;
+
+        let Some(size) = self.textures.get_mut(texture_id.id()) else {
+            return Err(HudhookError::TextureNotFound(texture_id));
+        };
+        if *size != (width, height) {
+            return Err(HudhookError::TextureSizeMismatch {
+                expected: *size,
+                actual: (width, height),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl RenderEngine for MockRenderEngine {
+    type RenderTarget = ();
+
+    fn render(&mut self, draw_data: &DrawData, _render_target: Self::RenderTarget) -> Result<()> {
+        self.frames_rendered += 1;
+        self.draw_list_count = draw_data.draw_lists_count();
+        self.vertex_count = draw_data.total_vtx_count as usize;
+        self.index_count = draw_data.total_idx_count as usize;
+        Ok(())
+    }
+
+    fn setup_fonts(&mut self, ctx: &mut Context) -> Result<()> {
+        let fonts = ctx.fonts();
+        let fonts_texture = fonts.build_rgba32_texture();
+        fonts.tex_id = match self.font_texture {
+            Some(texture_id) => {
+                check_data_length(
+                    fonts_texture.data,
+                    fonts_texture.width,
+                    fonts_texture.height,
+                )?;
+                self.textures[texture_id.id()] = (fonts_texture.width, fonts_texture.height);
+                texture_id
+            }
+            None => self.load_texture(
+                fonts_texture.data,
+                fonts_texture.width,
+                fonts_texture.height,
+            )?,
+        };
+        self.font_texture = Some(fonts.tex_id);
+        Ok(())
+    }
+}
+
+fn check_data_length(data: &[u8], width: u32, height: u32) -> Result<()> {
+    let expected = width as usize * height as usize * 4;
+    if data.len() < expected {
+        return Err(HudhookError::TextureDataLength {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    Ok(())
+}
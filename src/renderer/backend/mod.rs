@@ -4,5 +4,6 @@ pub mod dx11;
 pub mod dx12;
 #[cfg(feature = "dx9")]
 pub mod dx9;
+pub mod mock;
 #[cfg(feature = "opengl3")]
 pub mod opengl3;
@@ -7,7 +7,7 @@ use gl::types::*;
 use imgui::{internal::RawWrapper, Context, DrawCmd, DrawData, DrawIdx, DrawVert, TextureId};
 use once_cell::sync::OnceCell;
 use windows::{
-    core::{s, Error, Result, HRESULT, PCSTR},
+    core::{s, PCSTR},
     Win32::{
         Foundation::{FARPROC, HINSTANCE},
         Graphics::OpenGL::*,
@@ -15,7 +15,12 @@ use windows::{
     },
 };
 
-use crate::{renderer::RenderEngine, util, RenderContext};
+use crate::{
+    error::{HudhookError, Result},
+    renderer::RenderEngine,
+    util,
+    RenderContext,
+};
 
 mod gl {
     #![allow(
@@ -64,6 +69,7 @@ pub struct OpenGl3RenderEngine {
     projection_buffer: [[f32; 4]; 4],
 
     texture_heap: TextureHeap,
+    font_texture: Option<TextureId>,
 }
 
 impl OpenGl3RenderEngine {
@@ -100,6 +106,7 @@ impl OpenGl3RenderEngine {
             index_buffer,
             projection_buffer,
             texture_heap,
+            font_texture: None,
         })
     }
 }
@@ -141,11 +148,24 @@ impl RenderEngine for OpenGl3RenderEngine {
     fn setup_fonts(&mut self, ctx: &mut Context) -> Result<()> {
         let fonts = ctx.fonts();
         let fonts_texture = fonts.build_rgba32_texture();
-        fonts.tex_id = self.load_texture(
-            fonts_texture.data,
-            fonts_texture.width,
-            fonts_texture.height,
-        )?;
+        fonts.tex_id = match self.font_texture {
+            Some(texture_id) => unsafe {
+                self.texture_heap.recreate_texture(
+                    &self.gl,
+                    texture_id,
+                    fonts_texture.data,
+                    fonts_texture.width,
+                    fonts_texture.height,
+                )?;
+                texture_id
+            },
+            None => self.load_texture(
+                fonts_texture.data,
+                fonts_texture.width,
+                fonts_texture.height,
+            )?,
+        };
+        self.font_texture = Some(fonts.tex_id);
         Ok(())
     }
 }
@@ -446,6 +466,43 @@ impl TextureHeap {
         Ok(id)
     }
 
+    unsafe fn recreate_texture(
+        &mut self,
+        gl: &gl::Gl,
+        texture_id: TextureId,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        let Some(texture) = self.textures.get_mut(texture_id.id()) else {
+            return Err(HudhookError::TextureNotFound(texture_id));
+        };
+
+        let mut bound_texture = 0;
+        gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut bound_texture);
+
+        gl.ActiveTexture(gl::TEXTURE0);
+        gl.BindTexture(gl::TEXTURE_2D, texture.gl_texture);
+
+        gl.TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as GLint,
+            width as GLint,
+            height as GLint,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_ptr() as *const c_void,
+        );
+        gl.BindTexture(gl::TEXTURE_2D, bound_texture as _);
+
+        texture.width = width;
+        texture.height = height;
+
+        Ok(())
+    }
+
     unsafe fn update_texture(
         &mut self,
         gl: &gl::Gl,
@@ -456,7 +513,10 @@ impl TextureHeap {
     ) -> Result<()> {
         let texture_info = self.get(texture);
         if texture_info.width != width || texture_info.height != height {
-            return Err(Error::from_hresult(HRESULT(-1)));
+            return Err(HudhookError::TextureSizeMismatch {
+                expected: (texture_info.width, texture_info.height),
+                actual: (width, height),
+            });
         }
 
         let mut bound_texture = 0;
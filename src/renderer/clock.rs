@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+const MIN_DELTA: Duration = Duration::from_micros(100);
+const MAX_DELTA: Duration = Duration::from_millis(100);
+const DEFAULT_DELTA: Duration = Duration::from_nanos(16_666_667);
+const FPS_WINDOW: usize = 120;
+
+pub(crate) trait Clock: Send {
+    fn now(&mut self) -> Duration;
+}
+
+pub(crate) struct SystemClock(Instant);
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&mut self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTiming {
+    pub frame_time: Duration,
+    pub delta_time: Duration,
+    pub fps: f32,
+}
+
+impl Default for FrameTiming {
+    fn default() -> Self {
+        Self {
+            frame_time: DEFAULT_DELTA,
+            delta_time: DEFAULT_DELTA,
+            fps: 1.0 / DEFAULT_DELTA.as_secs_f32(),
+        }
+    }
+}
+
+pub(crate) struct FrameClock {
+    clock: Box<dyn Clock>,
+    last: Option<Duration>,
+    samples: [Duration; FPS_WINDOW],
+    sample_idx: usize,
+    sample_count: usize,
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::with_clock(SystemClock::default())
+    }
+}
+
+impl FrameClock {
+    pub(crate) fn with_clock(clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: Box::new(clock),
+            last: None,
+            samples: [Duration::ZERO; FPS_WINDOW],
+            sample_idx: 0,
+            sample_count: 0,
+        }
+    }
+
+    pub(crate) fn tick(&mut self) -> FrameTiming {
+        let now = self.clock.now();
+        let frame_time = match self.last.replace(now) {
+            Some(last) => now.saturating_sub(last),
+            None => DEFAULT_DELTA,
+        };
+        let delta_time = frame_time.clamp(MIN_DELTA, MAX_DELTA);
+
+        self.samples[self.sample_idx] = delta_time;
+        self.sample_idx = (self.sample_idx + 1) % FPS_WINDOW;
+        self.sample_count = (self.sample_count + 1).min(FPS_WINDOW);
+
+        let total: Duration = self.samples[..self.sample_count].iter().sum();
+
+        FrameTiming {
+            frame_time,
+            delta_time,
+            fps: self.sample_count as f32 / total.as_secs_f32(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeClock(std::vec::IntoIter<Duration>);
+
+    impl Clock for FakeClock {
+        fn now(&mut self) -> Duration {
+            self.0.next().expect("fake clock ran out of ticks")
+        }
+    }
+
+    fn frame_clock(ticks_ms: &[u64]) -> FrameClock {
+        let ticks: Vec<_> = ticks_ms
+            .iter()
+            .copied()
+            .map(Duration::from_millis)
+            .collect();
+        FrameClock::with_clock(FakeClock(ticks.into_iter()))
+    }
+
+    #[test]
+    fn first_frame_uses_the_default_delta() {
+        let mut clock = frame_clock(&[5_000]);
+
+        assert_eq!(clock.tick(), FrameTiming::default());
+    }
+
+    #[test]
+    fn measures_time_between_frames() {
+        let mut clock = frame_clock(&[1_000, 1_020]);
+        clock.tick();
+
+        let timing = clock.tick();
+        assert_eq!(timing.frame_time, Duration::from_millis(20));
+        assert_eq!(timing.delta_time, Duration::from_millis(20));
+    }
+
+    #[test]
+    fn clamps_delta_but_reports_raw_frame_time() {
+        let mut clock = frame_clock(&[0, 5_000, 5_000, 4_000]);
+        clock.tick();
+
+        let stall = clock.tick();
+        assert_eq!(stall.frame_time, Duration::from_secs(5));
+        assert_eq!(stall.delta_time, MAX_DELTA);
+
+        let same_instant = clock.tick();
+        assert_eq!(same_instant.frame_time, Duration::ZERO);
+        assert_eq!(same_instant.delta_time, MIN_DELTA);
+
+        // A clock going backwards must not underflow.
+        let backwards = clock.tick();
+        assert_eq!(backwards.frame_time, Duration::ZERO);
+        assert_eq!(backwards.delta_time, MIN_DELTA);
+    }
+
+    #[test]
+    fn fps_averages_over_a_rolling_window() {
+        let mut ticks = vec![0];
+        ticks.extend((1..=FPS_WINDOW as u64).map(|i| i * 10));
+        ticks.extend((1..=FPS_WINDOW as u64).map(|i| FPS_WINDOW as u64 * 10 + i * 20));
+        let mut clock = frame_clock(&ticks);
+
+        // The default first frame is averaged in until it falls out of the window.
+        clock.tick();
+        let timing = clock.tick();
+        let expected = 2.0 / (DEFAULT_DELTA + Duration::from_millis(10)).as_secs_f32();
+        assert!((timing.fps - expected).abs() < 0.01, "{}", timing.fps);
+
+        for _ in 2..FPS_WINDOW {
+            clock.tick();
+        }
+        let timing = clock.tick();
+        assert!((timing.fps - 100.0).abs() < 0.01, "{}", timing.fps);
+
+        // Half of the window now holds 20 ms frames.
+        for _ in 1..FPS_WINDOW / 2 {
+            clock.tick();
+        }
+        let timing = clock.tick();
+        assert!((timing.fps - 1.0 / 0.015).abs() < 0.01, "{}", timing.fps);
+
+        for _ in 1..FPS_WINDOW / 2 {
+            clock.tick();
+        }
+        let timing = clock.tick();
+        assert!((timing.fps - 50.0).abs() < 0.01, "{}", timing.fps);
+    }
+}
@@ -0,0 +1,94 @@
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM},
+    UI::{
+        Input::Ime::{
+            ImmGetCompositionStringW,
+            ImmGetContext,
+            ImmReleaseContext,
+            ImmSetCandidateWindow,
+            ImmSetCompositionWindow,
+            CANDIDATEFORM,
+            CFS_CANDIDATEPOS,
+            CFS_FORCE_POSITION,
+            COMPOSITIONFORM,
+            GCS_RESULTSTR,
+        },
+        WindowsAndMessaging::{DefWindowProcW, WM_IME_COMPOSITION},
+    },
+};
+
+pub(crate) fn pack_pos(x: f32, y: f32) -> u64 {
+    ((x as i32 as u32 as u64) << 32) | (y as i32 as u32 as u64)
+}
+
+pub(crate) fn unpack_pos(pos: u64) -> (i32, i32) {
+    ((pos >> 32) as u32 as i32, pos as u32 as i32)
+}
+
+pub(crate) unsafe fn ime_wnd_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    (x, y): (i32, i32),
+    mut commit: impl FnMut(char),
+) -> LRESULT {
+    let himc = ImmGetContext(hwnd);
+    if himc.is_invalid() {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+
+    let pos = POINT {
+        x,
+        y,
+    };
+
+    let _ = ImmSetCompositionWindow(
+        himc,
+        &COMPOSITIONFORM {
+            dwStyle: CFS_FORCE_POSITION,
+            ptCurrentPos: pos,
+            ..Default::default()
+        },
+    );
+    let _ = ImmSetCandidateWindow(
+        himc,
+        &CANDIDATEFORM {
+            dwIndex: 0,
+            dwStyle: CFS_CANDIDATEPOS,
+            ptCurrentPos: pos,
+            ..Default::default()
+        },
+    );
+
+    let committed = msg == WM_IME_COMPOSITION && (lparam.0 as u32 & GCS_RESULTSTR.0) != 0;
+
+    if committed {
+        let len = ImmGetCompositionStringW(himc, GCS_RESULTSTR, None, 0);
+        if len > 0 {
+            let mut buf = vec![0u16; len as usize / 2];
+            let len = ImmGetCompositionStringW(
+                himc,
+                GCS_RESULTSTR,
+                Some(buf.as_mut_ptr() as *mut _),
+                len as u32,
+            );
+            buf.truncate(len.max(0) as usize / 2);
+            decode_utf16_lossy(&buf).for_each(&mut commit);
+        }
+    }
+
+    let _ = ImmReleaseContext(hwnd, himc);
+
+    if committed {
+        LRESULT(0)
+    } else {
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+}
+
+pub(crate) fn decode_utf16_lossy(units: &[u16]) -> impl Iterator<Item = char> + '_ {
+    char::decode_utf16(units.iter().copied())
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .take_while(|&c| c != '\0')
+}
@@ -0,0 +1,651 @@
+use imgui::{sys, Io, Key, MouseButton};
+use windows::Win32::{
+    Foundation::{LPARAM, WPARAM},
+    UI::{
+        Input::KeyboardAndMouse::*,
+        WindowsAndMessaging::{
+            UNICODE_NOCHAR,
+            WA_INACTIVE,
+            WHEEL_DELTA,
+            WM_ACTIVATE,
+            WM_ACTIVATEAPP,
+            WM_CHAR,
+            WM_KEYDOWN,
+            WM_KEYFIRST,
+            WM_KEYLAST,
+            WM_KEYUP,
+            WM_KILLFOCUS,
+            WM_LBUTTONDBLCLK,
+            WM_LBUTTONDOWN,
+            WM_LBUTTONUP,
+            WM_MBUTTONDBLCLK,
+            WM_MBUTTONDOWN,
+            WM_MBUTTONUP,
+            WM_MOUSEFIRST,
+            WM_MOUSEHWHEEL,
+            WM_MOUSELAST,
+            WM_MOUSEMOVE,
+            WM_MOUSEWHEEL,
+            WM_RBUTTONDBLCLK,
+            WM_RBUTTONDOWN,
+            WM_RBUTTONUP,
+            WM_SETFOCUS,
+            WM_SYSKEYDOWN,
+            WM_SYSKEYUP,
+            WM_UNICHAR,
+            WM_XBUTTONDBLCLK,
+            WM_XBUTTONDOWN,
+            WM_XBUTTONUP,
+            XBUTTON1,
+            XBUTTON2,
+        },
+    },
+};
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputPolicy {
+    #[default]
+    PassThrough,
+    BlockMouse,
+    BlockKeyboard,
+    BlockAllWhenFocused,
+}
+
+impl InputPolicy {
+    pub(crate) fn from_u8(v: u8) -> Self {
+        match v {
+            1 => InputPolicy::BlockMouse,
+            2 => InputPolicy::BlockKeyboard,
+            3 => InputPolicy::BlockAllWhenFocused,
+            _ => InputPolicy::PassThrough,
+        }
+    }
+
+    pub(crate) fn blocks(
+        self,
+        umsg: u32,
+        want_capture_mouse: bool,
+        want_capture_keyboard: bool,
+    ) -> bool {
+        let is_mouse = (WM_MOUSEFIRST..=WM_MOUSELAST).contains(&umsg);
+        let is_keyboard = (WM_KEYFIRST..=WM_KEYLAST).contains(&umsg);
+
+        // Releases always reach the game so it never sees a key or button stuck down.
+        if is_release(umsg) {
+            return false;
+        }
+
+        match self {
+            InputPolicy::PassThrough => false,
+            InputPolicy::BlockMouse => is_mouse && want_capture_mouse,
+            InputPolicy::BlockKeyboard => is_keyboard && want_capture_keyboard,
+            InputPolicy::BlockAllWhenFocused => {
+                (is_mouse || is_keyboard) && (want_capture_mouse || want_capture_keyboard)
+            }
+        }
+    }
+}
+
+pub(crate) fn is_release(umsg: u32) -> bool {
+    matches!(
+        umsg,
+        WM_KEYUP | WM_SYSKEYUP | WM_LBUTTONUP | WM_RBUTTONUP | WM_MBUTTONUP | WM_XBUTTONUP
+    )
+}
+
+// Every message `InputState::translate` turns into events.
+pub(crate) fn is_input_message(umsg: u32) -> bool {
+    matches!(
+        umsg,
+        WM_MOUSEMOVE
+            | WM_LBUTTONDOWN
+            | WM_LBUTTONDBLCLK
+            | WM_LBUTTONUP
+            | WM_RBUTTONDOWN
+            | WM_RBUTTONDBLCLK
+            | WM_RBUTTONUP
+            | WM_MBUTTONDOWN
+            | WM_MBUTTONDBLCLK
+            | WM_MBUTTONUP
+            | WM_XBUTTONDOWN
+            | WM_XBUTTONDBLCLK
+            | WM_XBUTTONUP
+            | WM_MOUSEWHEEL
+            | WM_MOUSEHWHEEL
+            | WM_KEYDOWN
+            | WM_SYSKEYDOWN
+            | WM_KEYUP
+            | WM_SYSKEYUP
+            | WM_CHAR
+            | WM_UNICHAR
+            | WM_SETFOCUS
+            | WM_KILLFOCUS
+            | WM_ACTIVATEAPP
+            | WM_ACTIVATE
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum InputEvent {
+    MousePos([f32; 2]),
+    MouseButton(MouseButton, bool),
+    MouseWheel([f32; 2]),
+    Key(Key, bool),
+    Char(char),
+    Focus(bool),
+}
+
+impl InputEvent {
+    pub(crate) fn apply(self, io: &mut Io) {
+        match self {
+            InputEvent::MousePos(pos) => io.add_mouse_pos_event(pos),
+            InputEvent::MouseButton(button, down) => io.add_mouse_button_event(button, down),
+            InputEvent::MouseWheel(wheel) => io.add_mouse_wheel_event(wheel),
+            InputEvent::Key(key, down) => io.add_key_event(key, down),
+            InputEvent::Char(c) => io.add_input_character(c),
+            InputEvent::Focus(focused) => unsafe {
+                // Losing focus makes imgui release every key and mouse button it thinks is held.
+                sys::ImGuiIO_AddFocusEvent(io as *mut Io as *mut sys::ImGuiIO, focused)
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct InputState {
+    ctrl: [bool; 2],
+    shift: [bool; 2],
+    alt: [bool; 2],
+    super_: [bool; 2],
+    high_surrogate: Option<u16>,
+}
+
+impl InputState {
+    pub(crate) fn translate(
+        &mut self,
+        umsg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+        mut emit: impl FnMut(InputEvent),
+    ) {
+        match umsg {
+            WM_MOUSEMOVE => emit(InputEvent::MousePos(lparam_pos(lparam))),
+            WM_LBUTTONDOWN | WM_LBUTTONDBLCLK => {
+                emit(InputEvent::MouseButton(MouseButton::Left, true))
+            }
+            WM_LBUTTONUP => emit(InputEvent::MouseButton(MouseButton::Left, false)),
+            WM_RBUTTONDOWN | WM_RBUTTONDBLCLK => {
+                emit(InputEvent::MouseButton(MouseButton::Right, true))
+            }
+            WM_RBUTTONUP => emit(InputEvent::MouseButton(MouseButton::Right, false)),
+            WM_MBUTTONDOWN | WM_MBUTTONDBLCLK => {
+                emit(InputEvent::MouseButton(MouseButton::Middle, true))
+            }
+            WM_MBUTTONUP => emit(InputEvent::MouseButton(MouseButton::Middle, false)),
+            WM_XBUTTONDOWN | WM_XBUTTONDBLCLK | WM_XBUTTONUP => {
+                let down = umsg != WM_XBUTTONUP;
+                match hiword(wparam.0) {
+                    XBUTTON1 => emit(InputEvent::MouseButton(MouseButton::Extra1, down)),
+                    XBUTTON2 => emit(InputEvent::MouseButton(MouseButton::Extra2, down)),
+                    _ => {}
+                }
+            }
+            WM_MOUSEWHEEL => emit(InputEvent::MouseWheel([0.0, wheel_delta(wparam)])),
+            WM_MOUSEHWHEEL => emit(InputEvent::MouseWheel([-wheel_delta(wparam), 0.0])),
+            WM_KEYDOWN | WM_SYSKEYDOWN | WM_KEYUP | WM_SYSKEYUP => {
+                let down = umsg == WM_KEYDOWN || umsg == WM_SYSKEYDOWN;
+                self.translate_key(VIRTUAL_KEY(wparam.0 as u16), lparam, down, &mut emit);
+            }
+            WM_CHAR => {
+                let unit = wparam.0 as u16;
+                match unit {
+                    0xD800..=0xDBFF => self.high_surrogate = Some(unit),
+                    0xDC00..=0xDFFF => {
+                        if let Some(high) = self.high_surrogate.take() {
+                            char::decode_utf16([high, unit])
+                                .filter_map(Result::ok)
+                                .for_each(|c| emit(InputEvent::Char(c)));
+                        }
+                    }
+                    _ => {
+                        self.high_surrogate = None;
+                        if let Some(c) = char::from_u32(unit as u32) {
+                            emit(InputEvent::Char(c));
+                        }
+                    }
+                }
+            }
+            // UNICODE_NOCHAR only probes whether the window understands WM_UNICHAR.
+            WM_UNICHAR if wparam.0 as u32 == UNICODE_NOCHAR => {}
+            WM_UNICHAR => {
+                if let Some(c) = char::from_u32(wparam.0 as u32) {
+                    emit(InputEvent::Char(c));
+                }
+            }
+            WM_SETFOCUS => self.set_focus(true, &mut emit),
+            WM_KILLFOCUS => self.set_focus(false, &mut emit),
+            WM_ACTIVATEAPP => self.set_focus(wparam.0 != 0, &mut emit),
+            WM_ACTIVATE => self.set_focus(loword(wparam.0) as u32 != WA_INACTIVE, &mut emit),
+            _ => {}
+        }
+    }
+
+    fn set_focus(&mut self, focused: bool, emit: &mut impl FnMut(InputEvent)) {
+        if !focused {
+            *self = Self::default();
+        }
+        emit(InputEvent::Focus(focused));
+    }
+
+    fn translate_key(
+        &mut self,
+        vk: VIRTUAL_KEY,
+        lparam: LPARAM,
+        down: bool,
+        emit: &mut impl FnMut(InputEvent),
+    ) {
+        let extended = (lparam.0 >> 24) & 1 == 1;
+        let scancode = ((lparam.0 >> 16) & 0xFF) as u8;
+
+        let vk = match vk {
+            VK_CONTROL if extended => VK_RCONTROL,
+            VK_CONTROL => VK_LCONTROL,
+            VK_MENU if extended => VK_RMENU,
+            VK_MENU => VK_LMENU,
+            VK_SHIFT if scancode == 0x36 => VK_RSHIFT,
+            VK_SHIFT => VK_LSHIFT,
+            VK_RETURN if extended => return emit(InputEvent::Key(Key::KeypadEnter, down)),
+            vk => vk,
+        };
+
+        let modifier = match vk {
+            VK_LCONTROL => Some((&mut self.ctrl[0], Key::ModCtrl)),
+            VK_RCONTROL => Some((&mut self.ctrl[1], Key::ModCtrl)),
+            VK_LSHIFT => Some((&mut self.shift[0], Key::ModShift)),
+            VK_RSHIFT => Some((&mut self.shift[1], Key::ModShift)),
+            VK_LMENU => Some((&mut self.alt[0], Key::ModAlt)),
+            VK_RMENU => Some((&mut self.alt[1], Key::ModAlt)),
+            VK_LWIN => Some((&mut self.super_[0], Key::ModSuper)),
+            VK_RWIN => Some((&mut self.super_[1], Key::ModSuper)),
+            _ => None,
+        };
+
+        if let Some((held, mod_key)) = modifier {
+            *held = down;
+            let any_held = match mod_key {
+                Key::ModCtrl => self.ctrl,
+                Key::ModShift => self.shift,
+                Key::ModAlt => self.alt,
+                _ => self.super_,
+            }
+            .contains(&true);
+            emit(InputEvent::Key(mod_key, any_held));
+        }
+
+        if let Some(key) = map_vkey(vk) {
+            emit(InputEvent::Key(key, down));
+        }
+    }
+}
+
+fn loword(v: usize) -> u16 {
+    (v & 0xFFFF) as u16
+}
+
+fn hiword(v: usize) -> u16 {
+    ((v >> 16) & 0xFFFF) as u16
+}
+
+fn lparam_pos(lparam: LPARAM) -> [f32; 2] {
+    let x = loword(lparam.0 as usize) as i16;
+    let y = hiword(lparam.0 as usize) as i16;
+    [x as f32, y as f32]
+}
+
+fn wheel_delta(wparam: WPARAM) -> f32 {
+    hiword(wparam.0) as i16 as f32 / WHEEL_DELTA as f32
+}
+
+pub(crate) fn map_vkey(vk: VIRTUAL_KEY) -> Option<Key> {
+    Some(match vk {
+        VK_TAB => Key::Tab,
+        VK_LEFT => Key::LeftArrow,
+        VK_RIGHT => Key::RightArrow,
+        VK_UP => Key::UpArrow,
+        VK_DOWN => Key::DownArrow,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_HOME => Key::Home,
+        VK_END => Key::End,
+        VK_INSERT => Key::Insert,
+        VK_DELETE => Key::Delete,
+        VK_BACK => Key::Backspace,
+        VK_SPACE => Key::Space,
+        VK_RETURN => Key::Enter,
+        VK_ESCAPE => Key::Escape,
+        VK_OEM_7 => Key::Apostrophe,
+        VK_OEM_COMMA => Key::Comma,
+        VK_OEM_MINUS => Key::Minus,
+        VK_OEM_PERIOD => Key::Period,
+        VK_OEM_2 => Key::Slash,
+        VK_OEM_1 => Key::Semicolon,
+        VK_OEM_PLUS => Key::Equal,
+        VK_OEM_4 => Key::LeftBracket,
+        VK_OEM_5 => Key::Backslash,
+        VK_OEM_6 => Key::RightBracket,
+        VK_OEM_3 => Key::GraveAccent,
+        VK_CAPITAL => Key::CapsLock,
+        VK_SCROLL => Key::ScrollLock,
+        VK_NUMLOCK => Key::NumLock,
+        VK_SNAPSHOT => Key::PrintScreen,
+        VK_PAUSE => Key::Pause,
+        VK_NUMPAD0 => Key::Keypad0,
+        VK_NUMPAD1 => Key::Keypad1,
+        VK_NUMPAD2 => Key::Keypad2,
+        VK_NUMPAD3 => Key::Keypad3,
+        VK_NUMPAD4 => Key::Keypad4,
+        VK_NUMPAD5 => Key::Keypad5,
+        VK_NUMPAD6 => Key::Keypad6,
+        VK_NUMPAD7 => Key::Keypad7,
+        VK_NUMPAD8 => Key::Keypad8,
+        VK_NUMPAD9 => Key::Keypad9,
+        VK_DECIMAL => Key::KeypadDecimal,
+        VK_DIVIDE => Key::KeypadDivide,
+        VK_MULTIPLY => Key::KeypadMultiply,
+        VK_SUBTRACT => Key::KeypadSubtract,
+        VK_ADD => Key::KeypadAdd,
+        VK_LSHIFT => Key::LeftShift,
+        VK_LCONTROL => Key::LeftCtrl,
+        VK_LMENU => Key::LeftAlt,
+        VK_LWIN => Key::LeftSuper,
+        VK_RSHIFT => Key::RightShift,
+        VK_RCONTROL => Key::RightCtrl,
+        VK_RMENU => Key::RightAlt,
+        VK_RWIN => Key::RightSuper,
+        VK_APPS => Key::Menu,
+        VK_0 => Key::Alpha0,
+        VK_1 => Key::Alpha1,
+        VK_2 => Key::Alpha2,
+        VK_3 => Key::Alpha3,
+        VK_4 => Key::Alpha4,
+        VK_5 => Key::Alpha5,
+        VK_6 => Key::Alpha6,
+        VK_7 => Key::Alpha7,
+        VK_8 => Key::Alpha8,
+        VK_9 => Key::Alpha9,
+        VK_A => Key::A,
+        VK_B => Key::B,
+        VK_C => Key::C,
+        VK_D => Key::D,
+        VK_E => Key::E,
+        VK_F => Key::F,
+        VK_G => Key::G,
+        VK_H => Key::H,
+        VK_I => Key::I,
+        VK_J => Key::J,
+        VK_K => Key::K,
+        VK_L => Key::L,
+        VK_M => Key::M,
+        VK_N => Key::N,
+        VK_O => Key::O,
+        VK_P => Key::P,
+        VK_Q => Key::Q,
+        VK_R => Key::R,
+        VK_S => Key::S,
+        VK_T => Key::T,
+        VK_U => Key::U,
+        VK_V => Key::V,
+        VK_W => Key::W,
+        VK_X => Key::X,
+        VK_Y => Key::Y,
+        VK_Z => Key::Z,
+        VK_F1 => Key::F1,
+        VK_F2 => Key::F2,
+        VK_F3 => Key::F3,
+        VK_F4 => Key::F4,
+        VK_F5 => Key::F5,
+        VK_F6 => Key::F6,
+        VK_F7 => Key::F7,
+        VK_F8 => Key::F8,
+        VK_F9 => Key::F9,
+        VK_F10 => Key::F10,
+        VK_F11 => Key::F11,
+        VK_F12 => Key::F12,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENDED: isize = 1 << 24;
+
+    fn translate(
+        state: &mut InputState,
+        umsg: u32,
+        wparam: usize,
+        lparam: isize,
+    ) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        state.translate(umsg, WPARAM(wparam), LPARAM(lparam), |e| events.push(e));
+        events
+    }
+
+    fn key(state: &mut InputState, vk: VIRTUAL_KEY, lparam: isize, down: bool) -> Vec<InputEvent> {
+        let umsg = if down { WM_KEYDOWN } else { WM_KEYUP };
+        translate(state, umsg, vk.0 as usize, lparam)
+    }
+
+    #[test]
+    fn maps_virtual_keys() {
+        assert_eq!(map_vkey(VK_A), Some(Key::A));
+        assert_eq!(map_vkey(VK_9), Some(Key::Alpha9));
+        assert_eq!(map_vkey(VK_F12), Some(Key::F12));
+        assert_eq!(map_vkey(VK_NUMPAD5), Some(Key::Keypad5));
+        assert_eq!(map_vkey(VK_OEM_3), Some(Key::GraveAccent));
+        assert_eq!(map_vkey(VK_F13), None);
+
+        let mut state = InputState::default();
+        assert_eq!(
+            key(&mut state, VK_ESCAPE, 0, true),
+            [InputEvent::Key(Key::Escape, true)]
+        );
+        assert_eq!(
+            key(&mut state, VK_ESCAPE, 0, false),
+            [InputEvent::Key(Key::Escape, false)]
+        );
+    }
+
+    #[test]
+    fn tracks_left_and_right_modifiers() {
+        let mut state = InputState::default();
+
+        assert_eq!(
+            key(&mut state, VK_CONTROL, 0, true),
+            [
+                InputEvent::Key(Key::ModCtrl, true),
+                InputEvent::Key(Key::LeftCtrl, true)
+            ]
+        );
+        assert_eq!(
+            key(&mut state, VK_CONTROL, EXTENDED, true),
+            [
+                InputEvent::Key(Key::ModCtrl, true),
+                InputEvent::Key(Key::RightCtrl, true)
+            ]
+        );
+
+        // Releasing one side keeps the modifier held while the other side is down.
+        assert_eq!(
+            key(&mut state, VK_CONTROL, 0, false),
+            [
+                InputEvent::Key(Key::ModCtrl, true),
+                InputEvent::Key(Key::LeftCtrl, false)
+            ]
+        );
+        assert_eq!(
+            key(&mut state, VK_CONTROL, EXTENDED, false),
+            [
+                InputEvent::Key(Key::ModCtrl, false),
+                InputEvent::Key(Key::RightCtrl, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn distinguishes_shift_by_scancode_and_alt_by_extended_bit() {
+        let mut state = InputState::default();
+
+        assert_eq!(
+            key(&mut state, VK_SHIFT, 0x36 << 16, true),
+            [
+                InputEvent::Key(Key::ModShift, true),
+                InputEvent::Key(Key::RightShift, true)
+            ]
+        );
+        assert_eq!(
+            key(&mut state, VK_SHIFT, 0x2A << 16, true),
+            [
+                InputEvent::Key(Key::ModShift, true),
+                InputEvent::Key(Key::LeftShift, true)
+            ]
+        );
+        assert_eq!(
+            translate(&mut state, WM_SYSKEYDOWN, VK_MENU.0 as usize, EXTENDED),
+            [
+                InputEvent::Key(Key::ModAlt, true),
+                InputEvent::Key(Key::RightAlt, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn losing_focus_forgets_held_modifiers() {
+        let mut state = InputState::default();
+        key(&mut state, VK_CONTROL, 0, true);
+
+        assert_eq!(
+            translate(&mut state, WM_KILLFOCUS, 0, 0),
+            [InputEvent::Focus(false)]
+        );
+        assert_eq!(
+            key(&mut state, VK_CONTROL, EXTENDED, false),
+            [
+                InputEvent::Key(Key::ModCtrl, false),
+                InputEvent::Key(Key::RightCtrl, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn extended_return_is_keypad_enter() {
+        let mut state = InputState::default();
+
+        assert_eq!(
+            key(&mut state, VK_RETURN, EXTENDED, true),
+            [InputEvent::Key(Key::KeypadEnter, true)]
+        );
+        assert_eq!(
+            key(&mut state, VK_RETURN, 0, true),
+            [InputEvent::Key(Key::Enter, true)]
+        );
+    }
+
+    #[test]
+    fn joins_surrogate_pairs() {
+        let mut state = InputState::default();
+
+        assert_eq!(translate(&mut state, WM_CHAR, 0xD83D, 0), []);
+        assert_eq!(
+            translate(&mut state, WM_CHAR, 0xDE00, 0),
+            [InputEvent::Char('\u{1F600}')]
+        );
+        assert_eq!(
+            translate(&mut state, WM_CHAR, 'a' as usize, 0),
+            [InputEvent::Char('a')]
+        );
+    }
+
+    #[test]
+    fn drops_unpaired_surrogates() {
+        let mut state = InputState::default();
+
+        assert_eq!(translate(&mut state, WM_CHAR, 0xDE00, 0), []);
+        assert_eq!(translate(&mut state, WM_CHAR, 0xD83D, 0), []);
+        assert_eq!(
+            translate(&mut state, WM_CHAR, 'a' as usize, 0),
+            [InputEvent::Char('a')]
+        );
+        assert_eq!(translate(&mut state, WM_CHAR, 0xDE00, 0), []);
+    }
+
+    #[test]
+    fn unichar_ignores_nochar_probe() {
+        let mut state = InputState::default();
+
+        assert_eq!(
+            translate(&mut state, WM_UNICHAR, UNICODE_NOCHAR as usize, 0),
+            []
+        );
+        assert_eq!(
+            translate(&mut state, WM_UNICHAR, 0x1F600, 0),
+            [InputEvent::Char('\u{1F600}')]
+        );
+    }
+
+    #[test]
+    fn maps_xbuttons() {
+        let mut state = InputState::default();
+
+        assert_eq!(
+            translate(&mut state, WM_XBUTTONDOWN, (XBUTTON1 as usize) << 16, 0),
+            [InputEvent::MouseButton(MouseButton::Extra1, true)]
+        );
+        assert_eq!(
+            translate(&mut state, WM_XBUTTONDBLCLK, (XBUTTON2 as usize) << 16, 0),
+            [InputEvent::MouseButton(MouseButton::Extra2, true)]
+        );
+        assert_eq!(
+            translate(&mut state, WM_XBUTTONUP, (XBUTTON2 as usize) << 16, 0),
+            [InputEvent::MouseButton(MouseButton::Extra2, false)]
+        );
+    }
+
+    #[test]
+    fn wheel_sign_follows_imgui() {
+        let mut state = InputState::default();
+        let delta = |d: i16| (d as u16 as usize) << 16;
+
+        assert_eq!(
+            translate(&mut state, WM_MOUSEWHEEL, delta(WHEEL_DELTA as i16), 0),
+            [InputEvent::MouseWheel([0.0, 1.0])]
+        );
+        assert_eq!(
+            translate(
+                &mut state,
+                WM_MOUSEWHEEL,
+                delta(-(WHEEL_DELTA as i16) * 2),
+                0
+            ),
+            [InputEvent::MouseWheel([0.0, -2.0])]
+        );
+        // Windows reports tilting right as positive, imgui scrolls right on negative.
+        assert_eq!(
+            translate(&mut state, WM_MOUSEHWHEEL, delta(WHEEL_DELTA as i16), 0),
+            [InputEvent::MouseWheel([-1.0, 0.0])]
+        );
+    }
+
+    #[test]
+    fn mouse_position_is_signed() {
+        let mut state = InputState::default();
+        let lparam = ((-20i16 as u16 as isize) << 16) | (-10i16 as u16 as isize);
+
+        assert_eq!(
+            translate(&mut state, WM_MOUSEMOVE, 0, lparam),
+            [InputEvent::MousePos([-10.0, -20.0])]
+        );
+    }
+}
@@ -1,15 +1,22 @@
 mod backend;
+mod clock;
+mod ime;
+mod input;
 mod pipeline;
+mod pipeline_map;
+mod queue;
+mod raw_input;
 
 use imgui::{Context, DrawData};
-use windows::core::Result;
 
-use crate::RenderContext;
+use crate::{error::Result, RenderContext};
 
 pub(crate) trait RenderEngine: RenderContext {
     type RenderTarget;
 
     fn render(&mut self, draw_data: &DrawData, render_target: Self::RenderTarget) -> Result<()>;
+    // Called again whenever the atlas is rebuilt, which can change its size, so the font texture
+    // is recreated in place instead of adding another one.
     fn setup_fonts(&mut self, ctx: &mut Context) -> Result<()>;
 }
 #[cfg(feature = "dx11")]
@@ -18,6 +25,12 @@ pub(crate) use backend::dx11::D3D11RenderEngine;
 pub(crate) use backend::dx12::D3D12RenderEngine;
 #[cfg(feature = "dx9")]
 pub(crate) use backend::dx9::D3D9RenderEngine;
+pub use backend::mock::MockRenderEngine;
 #[cfg(feature = "opengl3")]
 pub(crate) use backend::opengl3::OpenGl3RenderEngine;
-pub(crate) use pipeline::Pipeline;
+pub(crate) use clock::Clock;
+pub use clock::FrameTiming;
+pub use input::InputPolicy;
+pub(crate) use pipeline::{message_queue_stats, Pipeline, PipelineMessage, WND_PROC_IN_FLIGHT};
+pub(crate) use pipeline_map::{PipelineMap, RenderLoopSource, CONTEXT_LOCK, RENDER_LOCK_TIMEOUT};
+pub use queue::MessageQueueStats;
@@ -1,123 +1,303 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     mem,
+    slice,
     sync::{
-        mpsc::{self, Receiver, Sender},
+        atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering},
         Arc,
     },
 };
 
-use imgui::Context;
+use imgui::{sys, Context, FontConfig, FontSource, MouseCursor};
 use once_cell::sync::{Lazy, OnceCell};
 use parking_lot::Mutex;
-use windows::{
-    core::{Error, Result, HRESULT},
-    Win32::{
-        Foundation::{HWND, LPARAM, LRESULT, WPARAM},
-        UI::WindowsAndMessaging::{
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+    UI::{
+        Input::{GetRawInputData, HRAWINPUT, RID_INPUT},
+        WindowsAndMessaging::{
             CallWindowProcW,
             DefWindowProcW,
+            GetWindowLongPtrA,
+            LoadCursorW,
+            SetCursor,
             SetWindowLongPtrA,
             GWLP_WNDPROC,
+            HCURSOR,
+            HTCLIENT,
+            IDC_ARROW,
+            IDC_HAND,
+            IDC_IBEAM,
+            IDC_NO,
+            IDC_SIZEALL,
+            IDC_SIZENESW,
+            IDC_SIZENS,
+            IDC_SIZENWSE,
+            IDC_SIZEWE,
+            USER_DEFAULT_SCREEN_DPI,
+            WM_DPICHANGED,
+            WM_IME_COMPOSITION,
+            WM_IME_ENDCOMPOSITION,
+            WM_IME_STARTCOMPOSITION,
+            WM_INPUT,
+            WM_KEYFIRST,
+            WM_KEYLAST,
+            WM_MOUSEFIRST,
+            WM_MOUSELAST,
+            WM_NCDESTROY,
+            WM_SETCURSOR,
             WM_SIZE,
+            WM_UNICHAR,
         },
     },
 };
 
-use crate::{renderer::RenderEngine, util, ImguiRenderLoop};
+use crate::{
+    clipboard,
+    error::{HudhookError, Result},
+    hotkeys,
+    renderer::{
+        clock::{Clock, FrameClock},
+        ime,
+        input::{self, InputEvent, InputState},
+        queue::{MessageQueue, MessageQueueStats, QUEUE_CAPACITY},
+        raw_input::{self, RawInputPacket, RAW_INPUT_HEADER_SIZE},
+        RenderEngine,
+    },
+    replay,
+    util::{self, InFlight},
+    ImguiRenderLoop,
+};
 
-type RenderLoop = Box<dyn ImguiRenderLoop + Send + Sync>;
+pub(crate) type RenderLoop = Box<dyn ImguiRenderLoop + Send + Sync>;
+
+const DEFAULT_FONT_SIZE: f32 = 13.0;
 
 pub type WndProcType =
     unsafe extern "system" fn(hwnd: HWND, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT;
 
 static mut PIPELINE_STATES: Lazy<Mutex<HashMap<isize, Arc<PipelineSharedState>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
+pub(crate) static WND_PROC_IN_FLIGHT: InFlight = InFlight::new();
+
+pub(crate) fn message_queue_stats() -> MessageQueueStats {
+    unsafe { PIPELINE_STATES.lock() }
+        .values()
+        .map(|shared_state| shared_state.queue.stats())
+        .fold(MessageQueueStats::default(), |total, stats| {
+            MessageQueueStats {
+                dropped: total.dropped + stats.dropped,
+                coalesced: total.coalesced + stats.coalesced,
+            }
+        })
+}
 
 #[derive(Debug)]
-pub(crate) struct PipelineMessage(pub(crate) u32, pub(crate) LPARAM);
+pub(crate) struct PipelineMessage(pub(crate) u32, pub(crate) WPARAM, pub(crate) LPARAM);
 
 pub(crate) struct PipelineSharedState {
     pub(crate) wnd_proc: WndProcType,
-    pub(crate) tx: Sender<PipelineMessage>,
+    pub(crate) queue: MessageQueue,
+    pub(crate) want_capture_mouse: AtomicBool,
+    pub(crate) want_capture_keyboard: AtomicBool,
+    pub(crate) legacy_mouse: AtomicBool,
+    pub(crate) legacy_keyboard: AtomicBool,
+    pub(crate) mouse_cursor: AtomicI32,
+    pub(crate) want_text_input: AtomicBool,
+    pub(crate) ime_pos: AtomicU64,
+    pub(crate) destroyed: AtomicBool,
+    pub(crate) enabled: OnceCell<Arc<AtomicBool>>,
+}
+
+impl PipelineSharedState {
+    fn is_enabled(&self) -> bool {
+        self.enabled
+            .get()
+            .is_none_or(|enabled| enabled.load(Ordering::SeqCst))
+    }
 }
 
 pub(crate) struct Pipeline<T: RenderEngine> {
     hwnd: HWND,
     ctx: Context,
+    raw_ctx: *mut sys::ImGuiContext,
     engine: T,
     render_loop: RenderLoop,
-    rx: Receiver<PipelineMessage>,
     shared_state: Arc<PipelineSharedState>,
-    queue_buffer: OnceCell<Vec<PipelineMessage>>,
+    queue_buffer: OnceCell<VecDeque<PipelineMessage>>,
+    input_state: InputState,
+    frame_clock: FrameClock,
+    dpi_scale: f32,
+    fonts_dirty: bool,
+    focused: bool,
+    visible: bool,
+    headless: bool,
 }
 
 impl<T: RenderEngine> Pipeline<T> {
     pub(crate) fn new(
+        hwnd: HWND,
+        ctx: Context,
+        engine: T,
+        render_loop: RenderLoop,
+        enabled: Arc<AtomicBool>,
+    ) -> Result<Self, (HudhookError, RenderLoop)> {
+        let (width, height) = match util::win_size(hwnd) {
+            Ok(size) => size,
+            Err(e) => return Err((e, render_loop)),
+        };
+        let dpi_scale = util::dpi_scale(hwnd);
+
+        let pipeline = Self::create(
+            hwnd,
+            ctx,
+            engine,
+            render_loop,
+            [width as f32, height as f32],
+            dpi_scale,
+            |hwnd| unsafe {
+                #[cfg(target_arch = "x86")]
+                type SwlpRet = i32;
+                #[cfg(target_arch = "x86_64")]
+                type SwlpRet = isize;
+
+                mem::transmute::<SwlpRet, WndProcType>(SetWindowLongPtrA(
+                    hwnd,
+                    GWLP_WNDPROC,
+                    pipeline_wnd_proc as usize as _,
+                ))
+            },
+        )?;
+
+        let _ = pipeline.shared_state.enabled.set(enabled);
+        unsafe { PIPELINE_STATES.lock() }.insert(hwnd.0, Arc::clone(&pipeline.shared_state));
+
+        Ok(pipeline)
+    }
+
+    pub(crate) fn headless(
+        ctx: Context,
+        engine: T,
+        render_loop: RenderLoop,
+        display_size: [f32; 2],
+        clock: impl Clock + 'static,
+    ) -> Result<Self, (HudhookError, RenderLoop)> {
+        let mut pipeline =
+            Self::create(HWND(0), ctx, engine, render_loop, display_size, 1.0, |_| {
+                headless_wnd_proc
+            })?;
+        pipeline.frame_clock = FrameClock::with_clock(clock);
+        pipeline.headless = true;
+
+        Ok(pipeline)
+    }
+
+    fn create(
         hwnd: HWND,
         mut ctx: Context,
         mut engine: T,
         mut render_loop: RenderLoop,
-    ) -> std::result::Result<Self, (Error, RenderLoop)> {
-        let (width, height) = util::win_size(hwnd);
+        display_size: [f32; 2],
+        dpi_scale: f32,
+        subclass: impl FnOnce(HWND) -> WndProcType,
+    ) -> Result<Self, (HudhookError, RenderLoop)> {
+        let raw_ctx = unsafe { sys::igGetCurrentContext() };
 
-        ctx.io_mut().display_size = [width as f32, height as f32];
+        ctx.io_mut().display_size = display_size;
+        ctx.io_mut().set_platform_ime_data_fn = Some(set_platform_ime_data);
+        ctx.set_clipboard_backend(clipboard::create_backend());
+        unsafe { (*sys::igGetMainViewport()).PlatformHandleRaw = hwnd.0 as _ };
 
         unsafe { render_loop.initialize(&mut ctx, &mut engine) };
 
+        if dpi_scale != 1.0 {
+            apply_dpi_scale(&mut ctx, &mut render_loop, 1.0, dpi_scale);
+        }
+
         if let Err(e) = engine.setup_fonts(&mut ctx) {
             return Err((e, render_loop));
         }
 
-        let wnd_proc = unsafe {
-            #[cfg(target_arch = "x86")]
-            type SwlpRet = i32;
-            #[cfg(target_arch = "x86_64")]
-            type SwlpRet = isize;
-
-            mem::transmute::<SwlpRet, WndProcType>(SetWindowLongPtrA(
-                hwnd,
-                GWLP_WNDPROC,
-                pipeline_wnd_proc as usize as _,
-            ))
-        };
+        let wnd_proc = subclass(hwnd);
 
-        let (tx, rx) = mpsc::channel();
         let shared_state = Arc::new(PipelineSharedState {
             wnd_proc,
-            tx,
+            queue: MessageQueue::new(QUEUE_CAPACITY),
+            want_capture_mouse: AtomicBool::new(false),
+            want_capture_keyboard: AtomicBool::new(false),
+            legacy_mouse: AtomicBool::new(false),
+            legacy_keyboard: AtomicBool::new(false),
+            mouse_cursor: AtomicI32::new(MouseCursor::Arrow as i32),
+            want_text_input: AtomicBool::new(false),
+            ime_pos: AtomicU64::new(0),
+            destroyed: AtomicBool::new(false),
+            enabled: OnceCell::new(),
         });
 
-        unsafe { PIPELINE_STATES.lock() }.insert(hwnd.0, Arc::clone(&shared_state));
-
-        let queue_buffer = OnceCell::from(Vec::new());
+        let queue_buffer = OnceCell::from(VecDeque::with_capacity(QUEUE_CAPACITY));
 
         Ok(Self {
             hwnd,
             ctx,
+            raw_ctx,
             engine,
             render_loop,
-            rx,
-            shared_state: Arc::clone(&shared_state),
+            shared_state,
             queue_buffer,
+            input_state: InputState::default(),
+            frame_clock: FrameClock::default(),
+            dpi_scale,
+            fonts_dirty: false,
+            focused: true,
+            visible: true,
+            headless: false,
         })
     }
 
     pub(crate) fn prepare_render(&mut self) -> Result<()> {
         let mut queue_buffer = self.queue_buffer.take().unwrap();
         queue_buffer.clear();
-        queue_buffer.extend(self.rx.try_iter());
+        self.shared_state.queue.drain_into(&mut queue_buffer);
+
+        if !self.headless {
+            replay::record_frame(self.hwnd.0, queue_buffer.make_contiguous());
+        }
+
+        // imgui only drains its input queue in NewFrame, which doesn't run while hidden, so input
+        // is still tracked but not forwarded until the overlay is shown again.
+        let visible = crate::overlay_visible();
+        if visible && !self.visible {
+            // Keys held when the overlay was hidden may have been released since.
+            let io = self.ctx.io_mut();
+            InputEvent::Focus(false).apply(io);
+            InputEvent::Focus(self.focused).apply(io);
+        }
+        self.visible = visible;
+
+        let mut dpi_changed = None;
+        let mut focus_changed = None;
         queue_buffer
             .drain(..)
-            .for_each(|PipelineMessage(umsg, lparam)| {
+            .for_each(|PipelineMessage(umsg, wparam, lparam)| {
+                let io = self.ctx.io_mut();
                 match umsg {
                     WM_SIZE => {
-                        self.ctx.io_mut().display_size = [
+                        io.display_size = [
                             (lparam.0 & 0xFFFF) as u16 as f32,
                             ((lparam.0 >> 16) & 0xFFFF) as u16 as f32,
                         ];
                     }
-                    _ => {}
+                    WM_DPICHANGED => {
+                        dpi_changed = Some((wparam.0 & 0xFFFF) as u16);
+                    }
+                    _ => self.input_state.translate(umsg, wparam, lparam, |event| {
+                        if let InputEvent::Focus(focused) = event {
+                            focus_changed = Some(focused);
+                        }
+                        if visible {
+                            event.apply(io)
+                        }
+                    }),
                 };
             });
 
@@ -125,11 +305,44 @@ impl<T: RenderEngine> Pipeline<T> {
             .set(queue_buffer)
             .expect("OnceCell should be empty");
 
+        if let Some(dpi) = dpi_changed {
+            let dpi_scale = dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32;
+            if dpi_scale != self.dpi_scale {
+                apply_dpi_scale(
+                    &mut self.ctx,
+                    &mut self.render_loop,
+                    self.dpi_scale,
+                    dpi_scale,
+                );
+                self.dpi_scale = dpi_scale;
+                self.fonts_dirty = true;
+            }
+        }
+
+        // Only the upload is retried if it fails; the atlas was already rebuilt for the new scale.
+        if self.fonts_dirty {
+            self.engine.setup_fonts(&mut self.ctx)?;
+            self.fonts_dirty = false;
+        }
+
+        if let Some(focused) = focus_changed {
+            if focused != self.focused {
+                self.focused = focused;
+                self.render_loop.on_focus_changed(focused);
+            }
+        }
+
+        let timing = self.frame_clock.tick();
+
         let io = self.ctx.io_mut();
 
+        io.update_delta_time(timing.delta_time);
+        io.mouse_draw_cursor = crate::software_cursor();
         io.nav_active = true;
         io.nav_visible = true;
 
+        self.render_loop.on_frame_timing(timing);
+
         unsafe {
             self.render_loop
                 .before_render(&mut self.ctx, &mut self.engine)
@@ -143,26 +356,92 @@ impl<T: RenderEngine> Pipeline<T> {
         let [fsw, fsh] = self.ctx.io().display_framebuffer_scale;
 
         if (w * fsw) <= 0.0 || (h * fsh) <= 0.0 {
-            return Err(Error::from_hresult(HRESULT(-1)));
+            return Err(HudhookError::InvalidDisplaySize([w * fsw, h * fsh]));
+        }
+
+        if !self.visible {
+            self.shared_state
+                .want_capture_mouse
+                .store(false, Ordering::SeqCst);
+            self.shared_state
+                .want_capture_keyboard
+                .store(false, Ordering::SeqCst);
+            return Ok(());
         }
 
         let ui = self.ctx.frame();
         unsafe { self.render_loop.render(ui) };
+        let mouse_cursor = ui.mouse_cursor().map(|c| c as i32).unwrap_or(-1);
         let draw_data = self.ctx.render();
 
         self.engine.render(draw_data, render_target)?;
 
+        let io = self.ctx.io();
+        self.shared_state
+            .want_capture_mouse
+            .store(io.want_capture_mouse, Ordering::SeqCst);
+        self.shared_state
+            .want_capture_keyboard
+            .store(io.want_capture_keyboard, Ordering::SeqCst);
+        self.shared_state
+            .want_text_input
+            .store(io.want_text_input, Ordering::SeqCst);
+        self.shared_state
+            .mouse_cursor
+            .store(mouse_cursor, Ordering::SeqCst);
+
         Ok(())
     }
 
+    pub(crate) fn push_message(&self, msg: PipelineMessage) {
+        self.shared_state.queue.push(msg);
+    }
+
+    pub(crate) fn engine(&self) -> &T {
+        &self.engine
+    }
+
+    pub(crate) fn make_current(&self) {
+        unsafe { sys::igSetCurrentContext(self.raw_ctx) };
+    }
+
+    pub(crate) fn is_destroyed(&self) -> bool {
+        self.shared_state.destroyed.load(Ordering::SeqCst)
+    }
+
     pub(crate) fn cleanup(&mut self) {
+        if self.headless {
+            return;
+        }
+
+        // A window subclassed after us keeps calling `pipeline_wnd_proc`, which then still needs
+        // the state to forward to the original procedure.
+        if self.restore_wnd_proc() {
+            unsafe { PIPELINE_STATES.lock() }.remove(&self.hwnd.0);
+        }
+    }
+
+    // Puts the original window procedure back without tearing down any state, which may still be
+    // in use by a thread that is inside one of our detours. Returns false if someone else has
+    // subclassed the window since, as restoring ours would unhook theirs.
+    pub(crate) fn restore_wnd_proc(&self) -> bool {
+        if self.headless || self.is_destroyed() {
+            return true;
+        }
+
         unsafe {
+            if GetWindowLongPtrA(self.hwnd, GWLP_WNDPROC) != pipeline_wnd_proc as usize as isize {
+                return false;
+            }
+
             SetWindowLongPtrA(
                 self.hwnd,
                 GWLP_WNDPROC,
                 self.shared_state.wnd_proc as usize as _,
-            )
-        };
+            );
+        }
+
+        true
     }
 
     pub(crate) fn take(mut self) -> RenderLoop {
@@ -171,12 +450,41 @@ impl<T: RenderEngine> Pipeline<T> {
     }
 }
 
+fn apply_dpi_scale(
+    ctx: &mut Context,
+    render_loop: &mut RenderLoop,
+    old_scale: f32,
+    new_scale: f32,
+) {
+    let auto_scale = crate::dpi_auto_scale();
+    if auto_scale {
+        ctx.style_mut().scale_all_sizes(new_scale / old_scale);
+        ctx.fonts().clear();
+    }
+
+    // With auto-scaling the render loop re-adds its fonts at the new size here, before the atlas
+    // is rebuilt.
+    render_loop.on_dpi_scale_changed(ctx, new_scale);
+
+    let fonts = ctx.fonts();
+    if auto_scale && fonts.fonts().is_empty() {
+        fonts.add_font(&[FontSource::DefaultFontData {
+            config: Some(FontConfig {
+                size_pixels: (DEFAULT_FONT_SIZE * new_scale).round(),
+                ..FontConfig::default()
+            }),
+        }]);
+    }
+}
+
 unsafe extern "system" fn pipeline_wnd_proc(
     hwnd: HWND,
     msg: u32,
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
+    let _in_flight = WND_PROC_IN_FLIGHT.enter();
+
     let shared_state = {
         let Some(shared_state_guard) = PIPELINE_STATES.try_lock() else {
             return DefWindowProcW(hwnd, msg, wparam, lparam);
@@ -189,7 +497,178 @@ unsafe extern "system" fn pipeline_wnd_proc(
         Arc::clone(shared_state)
     };
 
-    let _ = shared_state.tx.send(PipelineMessage(msg, lparam));
+    if msg == WM_NCDESTROY {
+        shared_state.destroyed.store(true, Ordering::SeqCst);
+    }
+
+    if !shared_state.is_enabled() {
+        return CallWindowProcW(Some(shared_state.wnd_proc), hwnd, msg, wparam, lparam);
+    }
+
+    if is_consumed(msg) {
+        shared_state
+            .queue
+            .push(PipelineMessage(msg, wparam, lparam));
+    }
+
+    if hotkeys::handle_message(msg, wparam, lparam) {
+        return LRESULT(0);
+    }
+
+    let policy = crate::active_input_policy();
+    let want_capture_mouse = shared_state.want_capture_mouse.load(Ordering::SeqCst);
+    let want_capture_keyboard = shared_state.want_capture_keyboard.load(Ordering::SeqCst);
+
+    if (WM_MOUSEFIRST..=WM_MOUSELAST).contains(&msg) {
+        shared_state.legacy_mouse.store(true, Ordering::SeqCst);
+    } else if (WM_KEYFIRST..=WM_KEYLAST).contains(&msg) {
+        shared_state.legacy_keyboard.store(true, Ordering::SeqCst);
+    }
+
+    if msg == WM_SETCURSOR
+        && (lparam.0 & 0xFFFF) as u32 == HTCLIENT
+        && want_capture_mouse
+        && crate::overlay_visible()
+    {
+        update_os_cursor(shared_state.mouse_cursor.load(Ordering::SeqCst));
+        return LRESULT(1);
+    }
+
+    if matches!(
+        msg,
+        WM_IME_STARTCOMPOSITION | WM_IME_COMPOSITION | WM_IME_ENDCOMPOSITION
+    ) && crate::overlay_visible()
+        && shared_state.want_text_input.load(Ordering::SeqCst)
+    {
+        let ime_pos = ime::unpack_pos(shared_state.ime_pos.load(Ordering::SeqCst));
+        return ime::ime_wnd_proc(hwnd, msg, wparam, lparam, ime_pos, |c| {
+            shared_state
+                .queue
+                .push(PipelineMessage(WM_UNICHAR, WPARAM(c as usize), LPARAM(0)));
+        });
+    }
+
+    if msg == WM_INPUT {
+        if let Some(packet) = read_raw_input(lparam) {
+            forward_raw_input(&shared_state, hwnd, packet);
+
+            if crate::raw_input_capture()
+                && policy.blocks(
+                    packet.legacy_equivalent(),
+                    want_capture_mouse,
+                    want_capture_keyboard,
+                )
+            {
+                return DefWindowProcW(hwnd, msg, wparam, lparam);
+            }
+        }
+    } else if policy.blocks(msg, want_capture_mouse, want_capture_keyboard) {
+        return LRESULT(0);
+    }
 
     CallWindowProcW(Some(shared_state.wnd_proc), hwnd, msg, wparam, lparam)
 }
+
+unsafe extern "system" fn headless_wnd_proc(
+    _hwnd: HWND,
+    _msg: u32,
+    _wparam: WPARAM,
+    _lparam: LPARAM,
+) -> LRESULT {
+    LRESULT(0)
+}
+
+unsafe extern "C" fn set_platform_ime_data(
+    viewport: *mut sys::ImGuiViewport,
+    data: *mut sys::ImGuiPlatformImeData,
+) {
+    let hwnd = (*viewport).PlatformHandleRaw as isize;
+
+    let Some(shared_state_guard) = PIPELINE_STATES.try_lock() else {
+        return;
+    };
+
+    if let Some(shared_state) = shared_state_guard.get(&hwnd) {
+        let data = &*data;
+        shared_state.ime_pos.store(
+            ime::pack_pos(data.InputPos.x, data.InputPos.y),
+            Ordering::SeqCst,
+        );
+    }
+}
+
+unsafe fn update_os_cursor(mouse_cursor: i32) {
+    let cursor = match usize::try_from(mouse_cursor)
+        .ok()
+        .and_then(|i| MouseCursor::VARIANTS.get(i))
+    {
+        _ if crate::software_cursor() => None,
+        None => None,
+        Some(MouseCursor::Arrow) => Some(IDC_ARROW),
+        Some(MouseCursor::TextInput) => Some(IDC_IBEAM),
+        Some(MouseCursor::ResizeAll) => Some(IDC_SIZEALL),
+        Some(MouseCursor::ResizeNS) => Some(IDC_SIZENS),
+        Some(MouseCursor::ResizeEW) => Some(IDC_SIZEWE),
+        Some(MouseCursor::ResizeNESW) => Some(IDC_SIZENESW),
+        Some(MouseCursor::ResizeNWSE) => Some(IDC_SIZENWSE),
+        Some(MouseCursor::Hand) => Some(IDC_HAND),
+        Some(MouseCursor::NotAllowed) => Some(IDC_NO),
+    };
+
+    let hcursor = cursor
+        .and_then(|cursor| LoadCursorW(None, cursor).ok())
+        .unwrap_or(HCURSOR(0));
+
+    SetCursor(hcursor);
+}
+
+unsafe fn read_raw_input(lparam: LPARAM) -> Option<RawInputPacket> {
+    let mut buf = [0u64; 8];
+    let mut size = mem::size_of_val(&buf) as u32;
+
+    let read = GetRawInputData(
+        HRAWINPUT(lparam.0),
+        RID_INPUT,
+        Some(buf.as_mut_ptr() as *mut _),
+        &mut size,
+        RAW_INPUT_HEADER_SIZE as u32,
+    );
+
+    if read == 0 || read == u32::MAX {
+        return None;
+    }
+
+    raw_input::decode(slice::from_raw_parts(
+        buf.as_ptr() as *const u8,
+        read as usize,
+    ))
+}
+
+// Only what `prepare_render` reads is queued, so e.g. WM_PAINT or WM_TIMER can't crowd out input.
+fn is_consumed(umsg: u32) -> bool {
+    matches!(umsg, WM_SIZE | WM_DPICHANGED) || input::is_input_message(umsg)
+}
+
+fn forward_raw_input(shared_state: &PipelineSharedState, hwnd: HWND, packet: RawInputPacket) {
+    let legacy = if packet.is_mouse() {
+        &shared_state.legacy_mouse
+    } else {
+        &shared_state.legacy_keyboard
+    };
+
+    if legacy.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let cursor_pos = if packet.has_motion() {
+        util::cursor_pos(hwnd)
+    } else {
+        None
+    };
+
+    packet.to_messages(cursor_pos, |umsg, wparam, lparam| {
+        shared_state
+            .queue
+            .push(PipelineMessage(umsg, wparam, lparam));
+    });
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    ptr,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use imgui::{sys, Context};
+use parking_lot::Mutex;
+use windows::Win32::Foundation::HWND;
+
+use crate::{
+    error::{HudhookError, Result},
+    renderer::{
+        pipeline::{Pipeline, RenderLoop},
+        RenderEngine,
+    },
+};
+
+// imgui has a single current context per process, so only one pipeline may use it at a time.
+pub(crate) static CONTEXT_LOCK: Mutex<()> = parking_lot::const_mutex(());
+
+// Windows presenting on different threads take turns instead of skipping frames, while a thread
+// that re-enters Present during rendering still gives up instead of deadlocking.
+pub(crate) const RENDER_LOCK_TIMEOUT: Duration = Duration::from_millis(16);
+
+type RenderLoopFactory = Box<dyn Fn(HWND) -> Option<RenderLoop> + Send + Sync>;
+
+pub(crate) enum RenderLoopSource {
+    Single(Option<RenderLoop>),
+    Factory(RenderLoopFactory, HashMap<isize, RenderLoop>),
+}
+
+impl RenderLoopSource {
+    pub(crate) fn single(render_loop: RenderLoop) -> Self {
+        RenderLoopSource::Single(Some(render_loop))
+    }
+
+    pub(crate) fn factory(
+        factory: impl Fn(HWND) -> Option<RenderLoop> + Send + Sync + 'static,
+    ) -> Self {
+        RenderLoopSource::Factory(Box::new(factory), HashMap::new())
+    }
+
+    fn acquire(&mut self, hwnd: HWND) -> Option<RenderLoop> {
+        match self {
+            RenderLoopSource::Single(slot) => {
+                if slot
+                    .as_ref()
+                    .is_some_and(|render_loop| render_loop.attach_to(hwnd))
+                {
+                    slot.take()
+                } else {
+                    None
+                }
+            }
+            RenderLoopSource::Factory(factory, parked) => {
+                parked.remove(&hwnd.0).or_else(|| factory(hwnd))
+            }
+        }
+    }
+
+    fn release(&mut self, hwnd: HWND, render_loop: RenderLoop, destroyed: bool) {
+        match self {
+            RenderLoopSource::Single(slot) => *slot = Some(render_loop),
+            RenderLoopSource::Factory(_, parked) => {
+                if !destroyed {
+                    parked.insert(hwnd.0, render_loop);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct PipelineMap<T: RenderEngine> {
+    pipelines: HashMap<isize, Pipeline<T>>,
+    source: RenderLoopSource,
+    enabled: Arc<AtomicBool>,
+}
+
+impl<T: RenderEngine> PipelineMap<T> {
+    pub(crate) fn new(source: RenderLoopSource, enabled: Arc<AtomicBool>) -> Self {
+        Self {
+            pipelines: HashMap::new(),
+            source,
+            enabled,
+        }
+    }
+
+    pub(crate) fn render(
+        &mut self,
+        hwnd: HWND,
+        init: impl FnOnce(&mut Context) -> Result<T>,
+        f: impl FnOnce(&mut Pipeline<T>) -> Result<()>,
+    ) -> Result<()> {
+        let Some(_guard) = CONTEXT_LOCK.try_lock_for(RENDER_LOCK_TIMEOUT) else {
+            return Err(HudhookError::LockContention);
+        };
+
+        self.sweep();
+
+        let pipeline = match self.pipelines.entry(hwnd.0) {
+            Entry::Occupied(entry) => {
+                let pipeline = entry.into_mut();
+                pipeline.make_current();
+                pipeline
+            }
+            Entry::Vacant(entry) => {
+                let Some(render_loop) = self.source.acquire(hwnd) else {
+                    return Err(HudhookError::NoRenderLoop);
+                };
+
+                let mut ctx = Context::create();
+                let engine = match init(&mut ctx) {
+                    Ok(engine) => engine,
+                    Err(e) => {
+                        self.source.release(hwnd, render_loop, false);
+                        return Err(e);
+                    }
+                };
+
+                match Pipeline::new(hwnd, ctx, engine, render_loop, Arc::clone(&self.enabled)) {
+                    Ok(pipeline) => entry.insert(pipeline),
+                    Err((e, render_loop)) => {
+                        self.source.release(hwnd, render_loop, false);
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        let result = f(pipeline);
+        unsafe { sys::igSetCurrentContext(ptr::null_mut()) };
+
+        result
+    }
+
+    pub(crate) fn reset(&mut self) {
+        let _guard = CONTEXT_LOCK.lock();
+
+        for (hwnd, pipeline) in self.pipelines.drain() {
+            self.source.release(HWND(hwnd), pipeline.take(), false);
+        }
+    }
+
+    // Returns how many windows are still subclassed by us.
+    pub(crate) fn restore_wnd_procs(&self) -> usize {
+        self.pipelines
+            .values()
+            .filter(|pipeline| !pipeline.restore_wnd_proc())
+            .count()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        let _guard = CONTEXT_LOCK.lock();
+
+        for (_, pipeline) in self.pipelines.drain() {
+            pipeline.take();
+        }
+    }
+
+    fn sweep(&mut self) {
+        let destroyed: Vec<isize> = self
+            .pipelines
+            .iter()
+            .filter(|(_, pipeline)| pipeline.is_destroyed())
+            .map(|(&hwnd, _)| hwnd)
+            .collect();
+
+        for hwnd in destroyed {
+            if let Some(pipeline) = self.pipelines.remove(&hwnd) {
+                self.source.release(HWND(hwnd), pipeline.take(), true);
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::VecDeque,
+    hint,
+    mem,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use parking_lot::Mutex;
+use windows::Win32::UI::WindowsAndMessaging::{
+    WM_ACTIVATE,
+    WM_ACTIVATEAPP,
+    WM_DPICHANGED,
+    WM_KILLFOCUS,
+    WM_MOUSEMOVE,
+    WM_SETFOCUS,
+    WM_SIZE,
+};
+
+use crate::renderer::{input, pipeline::PipelineMessage};
+
+pub(crate) const QUEUE_CAPACITY: usize = 1024;
+
+// The render thread only holds the lock to swap two buffers, so a short spin is nearly always
+// enough.
+const PUSH_SPINS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MessageQueueStats {
+    pub dropped: u64,
+    pub coalesced: u64,
+}
+
+// Both buffers are allocated up front and swapped on drain, so neither side allocates while holding
+// the lock and the window procedure never parks on it.
+pub(crate) struct MessageQueue {
+    buf: Mutex<VecDeque<PipelineMessage>>,
+    capacity: usize,
+    dropped: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl MessageQueue {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            buf: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            dropped: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> MessageQueueStats {
+        MessageQueueStats {
+            dropped: self.dropped.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn push(&self, msg: PipelineMessage) {
+        let mut spins = 0;
+        let mut buf = loop {
+            if let Some(buf) = self.buf.try_lock() {
+                break buf;
+            }
+            spins += 1;
+            if spins < PUSH_SPINS {
+                hint::spin_loop();
+            } else if is_protected(msg.0) {
+                // Still bounded, as the render thread never does more than a swap under the lock.
+                break self.buf.lock();
+            } else {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        };
+
+        if let Some(last) = buf.back_mut() {
+            if last.0 == msg.0 && msg.0 == WM_MOUSEMOVE {
+                *last = msg;
+                self.coalesced.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+
+        // Only the newest size and scale matter, and keeping a single one means they never have to
+        // be evicted.
+        if is_latest_only(msg.0) {
+            if let Some(i) = buf.iter().position(|m| m.0 == msg.0) {
+                buf.remove(i);
+                self.coalesced.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        if buf.len() >= self.capacity {
+            let victim = buf
+                .iter()
+                .position(|m| m.0 == WM_MOUSEMOVE)
+                .or_else(|| buf.iter().position(|m| !is_protected(m.0)));
+            match victim {
+                Some(i) => {
+                    buf.remove(i);
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                // A full buffer of protected messages only grows to admit another protected one.
+                None if is_protected(msg.0) => {}
+                None => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+
+        buf.push_back(msg);
+    }
+
+    // `out` must be empty; it is handed to the window procedure side as the next buffer.
+    pub(crate) fn drain_into(&self, out: &mut VecDeque<PipelineMessage>) {
+        debug_assert!(out.is_empty());
+        mem::swap(&mut *self.buf.lock(), out);
+    }
+}
+
+fn is_latest_only(umsg: u32) -> bool {
+    matches!(umsg, WM_SIZE | WM_DPICHANGED)
+}
+
+// Losing one of these leaves imgui with a stale size, or keys and buttons stuck down.
+fn is_protected(umsg: u32) -> bool {
+    is_latest_only(umsg)
+        || input::is_release(umsg)
+        || matches!(
+            umsg,
+            WM_SETFOCUS | WM_KILLFOCUS | WM_ACTIVATE | WM_ACTIVATEAPP
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::{
+        Foundation::{LPARAM, WPARAM},
+        UI::WindowsAndMessaging::{WM_CHAR, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONUP},
+    };
+
+    use super::*;
+
+    fn msg(umsg: u32, lparam: isize) -> PipelineMessage {
+        PipelineMessage(umsg, WPARAM(0), LPARAM(lparam))
+    }
+
+    fn drain(queue: &MessageQueue) -> Vec<(u32, isize)> {
+        let mut out = VecDeque::new();
+        queue.drain_into(&mut out);
+        out.into_iter().map(|m| (m.0, m.2 .0)).collect()
+    }
+
+    #[test]
+    fn keeps_fifo_order() {
+        let queue = MessageQueue::new(8);
+        queue.push(msg(WM_KEYDOWN, 1));
+        queue.push(msg(WM_CHAR, 2));
+        queue.push(msg(WM_KEYUP, 3));
+
+        assert_eq!(
+            drain(&queue),
+            [(WM_KEYDOWN, 1), (WM_CHAR, 2), (WM_KEYUP, 3)]
+        );
+        assert!(drain(&queue).is_empty());
+    }
+
+    #[test]
+    fn coalesces_consecutive_moves_and_sizes() {
+        let queue = MessageQueue::new(8);
+        queue.push(msg(WM_MOUSEMOVE, 1));
+        queue.push(msg(WM_MOUSEMOVE, 2));
+        queue.push(msg(WM_MOUSEMOVE, 3));
+        queue.push(msg(WM_SIZE, 4));
+        queue.push(msg(WM_SIZE, 5));
+
+        assert_eq!(drain(&queue), [(WM_MOUSEMOVE, 3), (WM_SIZE, 5)]);
+        assert_eq!(
+            queue.stats(),
+            MessageQueueStats {
+                dropped: 0,
+                coalesced: 3
+            }
+        );
+    }
+
+    #[test]
+    fn does_not_coalesce_moves_across_other_messages() {
+        let queue = MessageQueue::new(8);
+        queue.push(msg(WM_MOUSEMOVE, 1));
+        queue.push(msg(WM_LBUTTONUP, 2));
+        queue.push(msg(WM_MOUSEMOVE, 3));
+        queue.push(msg(WM_KEYDOWN, 4));
+        queue.push(msg(WM_KEYDOWN, 5));
+
+        assert_eq!(
+            drain(&queue),
+            [
+                (WM_MOUSEMOVE, 1),
+                (WM_LBUTTONUP, 2),
+                (WM_MOUSEMOVE, 3),
+                (WM_KEYDOWN, 4),
+                (WM_KEYDOWN, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_only_the_newest_size() {
+        let queue = MessageQueue::new(8);
+        queue.push(msg(WM_SIZE, 1));
+        queue.push(msg(WM_CHAR, 2));
+        queue.push(msg(WM_SIZE, 3));
+
+        assert_eq!(drain(&queue), [(WM_CHAR, 2), (WM_SIZE, 3)]);
+        assert_eq!(queue.stats().coalesced, 1);
+    }
+
+    #[test]
+    fn drops_the_oldest_message_at_capacity() {
+        let queue = MessageQueue::new(3);
+        for i in 0..5 {
+            queue.push(msg(WM_CHAR, i));
+        }
+
+        assert_eq!(drain(&queue), [(WM_CHAR, 2), (WM_CHAR, 3), (WM_CHAR, 4)]);
+        assert_eq!(queue.stats().dropped, 2);
+    }
+
+    #[test]
+    fn evicts_coalescable_messages_first() {
+        let queue = MessageQueue::new(3);
+        queue.push(msg(WM_CHAR, 1));
+        queue.push(msg(WM_MOUSEMOVE, 2));
+        queue.push(msg(WM_CHAR, 3));
+        queue.push(msg(WM_CHAR, 4));
+
+        assert_eq!(drain(&queue), [(WM_CHAR, 1), (WM_CHAR, 3), (WM_CHAR, 4)]);
+        assert_eq!(queue.stats().dropped, 1);
+    }
+
+    #[test]
+    fn never_evicts_releases() {
+        let queue = MessageQueue::new(3);
+        queue.push(msg(WM_KEYUP, 1));
+        queue.push(msg(WM_LBUTTONUP, 2));
+        queue.push(msg(WM_KEYDOWN, 3));
+        queue.push(msg(WM_KEYDOWN, 4));
+        queue.push(msg(WM_KEYUP, 5));
+
+        assert_eq!(
+            drain(&queue),
+            [(WM_KEYUP, 1), (WM_LBUTTONUP, 2), (WM_KEYUP, 5)]
+        );
+        assert_eq!(queue.stats().dropped, 2);
+    }
+
+    #[test]
+    fn never_evicts_focus_changes_or_sizes() {
+        let queue = MessageQueue::new(3);
+        queue.push(msg(WM_KILLFOCUS, 1));
+        queue.push(msg(WM_SIZE, 2));
+        queue.push(msg(WM_CHAR, 3));
+        queue.push(msg(WM_ACTIVATE, 4));
+
+        assert_eq!(
+            drain(&queue),
+            [(WM_KILLFOCUS, 1), (WM_SIZE, 2), (WM_ACTIVATE, 4)]
+        );
+        assert_eq!(queue.stats().dropped, 1);
+    }
+
+    #[test]
+    fn always_admits_releases() {
+        let queue = MessageQueue::new(2);
+        queue.push(msg(WM_KEYUP, 1));
+        queue.push(msg(WM_LBUTTONUP, 2));
+        queue.push(msg(WM_KEYUP, 3));
+        queue.push(msg(WM_CHAR, 4));
+
+        assert_eq!(
+            drain(&queue),
+            [(WM_KEYUP, 1), (WM_LBUTTONUP, 2), (WM_KEYUP, 3)]
+        );
+        assert_eq!(queue.stats().dropped, 1);
+    }
+}
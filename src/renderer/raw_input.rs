@@ -0,0 +1,342 @@
+use std::mem;
+
+use windows::Win32::{
+    Foundation::{LPARAM, WPARAM},
+    UI::{
+        Input::{
+            KeyboardAndMouse::{VK_F10, VK_MENU},
+            RAWINPUTHEADER,
+            RIM_TYPEKEYBOARD,
+            RIM_TYPEMOUSE,
+        },
+        WindowsAndMessaging::{
+            RI_KEY_BREAK,
+            RI_KEY_E0,
+            RI_MOUSE_BUTTON_4_DOWN,
+            RI_MOUSE_BUTTON_4_UP,
+            RI_MOUSE_BUTTON_5_DOWN,
+            RI_MOUSE_BUTTON_5_UP,
+            RI_MOUSE_HWHEEL,
+            RI_MOUSE_LEFT_BUTTON_DOWN,
+            RI_MOUSE_LEFT_BUTTON_UP,
+            RI_MOUSE_MIDDLE_BUTTON_DOWN,
+            RI_MOUSE_MIDDLE_BUTTON_UP,
+            RI_MOUSE_RIGHT_BUTTON_DOWN,
+            RI_MOUSE_RIGHT_BUTTON_UP,
+            RI_MOUSE_WHEEL,
+            WM_KEYDOWN,
+            WM_KEYUP,
+            WM_LBUTTONDOWN,
+            WM_LBUTTONUP,
+            WM_MBUTTONDOWN,
+            WM_MBUTTONUP,
+            WM_MOUSEHWHEEL,
+            WM_MOUSEMOVE,
+            WM_MOUSEWHEEL,
+            WM_RBUTTONDOWN,
+            WM_RBUTTONUP,
+            WM_SYSKEYDOWN,
+            WM_SYSKEYUP,
+            WM_XBUTTONDOWN,
+            WM_XBUTTONUP,
+            XBUTTON1,
+            XBUTTON2,
+        },
+    },
+};
+
+pub(crate) const RAW_INPUT_HEADER_SIZE: usize = mem::size_of::<RAWINPUTHEADER>();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RawInputPacket {
+    Mouse {
+        last_x: i32,
+        last_y: i32,
+        button_flags: u16,
+        button_data: i16,
+    },
+    Keyboard {
+        make_code: u16,
+        flags: u16,
+        vkey: u16,
+    },
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(buf.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(buf.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+pub(crate) fn decode(buf: &[u8]) -> Option<RawInputPacket> {
+    let ty = read_u32(buf, 0)?;
+    let data = RAW_INPUT_HEADER_SIZE;
+
+    if ty == RIM_TYPEMOUSE.0 {
+        Some(RawInputPacket::Mouse {
+            button_flags: read_u16(buf, data + 4)?,
+            button_data: read_u16(buf, data + 6)? as i16,
+            last_x: read_u32(buf, data + 12)? as i32,
+            last_y: read_u32(buf, data + 16)? as i32,
+        })
+    } else if ty == RIM_TYPEKEYBOARD.0 {
+        Some(RawInputPacket::Keyboard {
+            make_code: read_u16(buf, data)?,
+            flags: read_u16(buf, data + 2)?,
+            vkey: read_u16(buf, data + 6)?,
+        })
+    } else {
+        None
+    }
+}
+
+impl RawInputPacket {
+    pub(crate) fn is_mouse(&self) -> bool {
+        matches!(self, RawInputPacket::Mouse { .. })
+    }
+
+    pub(crate) fn has_motion(&self) -> bool {
+        matches!(self, RawInputPacket::Mouse { last_x, last_y, .. } if *last_x != 0 || *last_y != 0)
+    }
+
+    pub(crate) fn legacy_equivalent(&self) -> u32 {
+        const RELEASES: u32 = RI_MOUSE_LEFT_BUTTON_UP
+            | RI_MOUSE_RIGHT_BUTTON_UP
+            | RI_MOUSE_MIDDLE_BUTTON_UP
+            | RI_MOUSE_BUTTON_4_UP
+            | RI_MOUSE_BUTTON_5_UP;
+
+        match *self {
+            RawInputPacket::Mouse {
+                button_flags, ..
+            } if button_flags as u32 & RELEASES != 0 => WM_LBUTTONUP,
+            RawInputPacket::Mouse {
+                ..
+            } => WM_MOUSEMOVE,
+            RawInputPacket::Keyboard {
+                flags, ..
+            } if flags as u32 & RI_KEY_BREAK != 0 => WM_KEYUP,
+            RawInputPacket::Keyboard {
+                ..
+            } => WM_KEYDOWN,
+        }
+    }
+
+    pub(crate) fn to_messages(
+        self,
+        cursor_pos: Option<(i32, i32)>,
+        mut emit: impl FnMut(u32, WPARAM, LPARAM),
+    ) {
+        match self {
+            RawInputPacket::Mouse {
+                button_flags,
+                button_data,
+                ..
+            } => {
+                if let Some((x, y)) = cursor_pos {
+                    let lparam = ((y as u16 as isize) << 16) | (x as u16 as isize);
+                    emit(WM_MOUSEMOVE, WPARAM(0), LPARAM(lparam));
+                }
+
+                let button_flags = button_flags as u32;
+                let wheel = WPARAM(((button_data as u16 as usize) << 16) as _);
+                let xbutton = |b: u16| WPARAM((b as usize) << 16);
+
+                [
+                    (RI_MOUSE_LEFT_BUTTON_DOWN, WM_LBUTTONDOWN, WPARAM(0)),
+                    (RI_MOUSE_LEFT_BUTTON_UP, WM_LBUTTONUP, WPARAM(0)),
+                    (RI_MOUSE_RIGHT_BUTTON_DOWN, WM_RBUTTONDOWN, WPARAM(0)),
+                    (RI_MOUSE_RIGHT_BUTTON_UP, WM_RBUTTONUP, WPARAM(0)),
+                    (RI_MOUSE_MIDDLE_BUTTON_DOWN, WM_MBUTTONDOWN, WPARAM(0)),
+                    (RI_MOUSE_MIDDLE_BUTTON_UP, WM_MBUTTONUP, WPARAM(0)),
+                    (RI_MOUSE_BUTTON_4_DOWN, WM_XBUTTONDOWN, xbutton(XBUTTON1)),
+                    (RI_MOUSE_BUTTON_4_UP, WM_XBUTTONUP, xbutton(XBUTTON1)),
+                    (RI_MOUSE_BUTTON_5_DOWN, WM_XBUTTONDOWN, xbutton(XBUTTON2)),
+                    (RI_MOUSE_BUTTON_5_UP, WM_XBUTTONUP, xbutton(XBUTTON2)),
+                    (RI_MOUSE_WHEEL, WM_MOUSEWHEEL, wheel),
+                    (RI_MOUSE_HWHEEL, WM_MOUSEHWHEEL, wheel),
+                ]
+                .into_iter()
+                .filter(|(flag, ..)| button_flags & flag != 0)
+                .for_each(|(_, umsg, wparam)| emit(umsg, wparam, LPARAM(0)));
+            }
+            RawInputPacket::Keyboard {
+                make_code,
+                flags,
+                vkey,
+            } => {
+                let flags = flags as u32;
+                let up = flags & RI_KEY_BREAK != 0;
+                let extended = flags & RI_KEY_E0 != 0;
+
+                let mut lparam = 1 | ((make_code as isize & 0xFF) << 16);
+                if extended {
+                    lparam |= 1 << 24;
+                }
+                if up {
+                    lparam |= (1 << 30) | (1 << 31);
+                }
+
+                let sys = vkey == VK_MENU.0 || vkey == VK_F10.0;
+                let umsg = match (up, sys) {
+                    (false, false) => WM_KEYDOWN,
+                    (true, false) => WM_KEYUP,
+                    (false, true) => WM_SYSKEYDOWN,
+                    (true, true) => WM_SYSKEYUP,
+                };
+
+                emit(umsg, WPARAM(vkey as usize), LPARAM(lparam));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::UI::{Input::KeyboardAndMouse::VK_A, WindowsAndMessaging::RI_KEY_MAKE};
+
+    use super::*;
+
+    fn packet(ty: u32, payload: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0u8; RAW_INPUT_HEADER_SIZE];
+        buf[..4].copy_from_slice(&ty.to_le_bytes());
+        buf[4..8].copy_from_slice(&((RAW_INPUT_HEADER_SIZE + payload.len()) as u32).to_le_bytes());
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    // RAWMOUSE: usFlags, padding, usButtonFlags, usButtonData, ulRawButtons, lLastX, lLastY,
+    // ulExtraInformation.
+    fn mouse(button_flags: u32, button_data: i16, last_x: i32, last_y: i32) -> Vec<u8> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&0u16.to_le_bytes());
+        payload.extend_from_slice(&0u16.to_le_bytes());
+        payload.extend_from_slice(&(button_flags as u16).to_le_bytes());
+        payload.extend_from_slice(&button_data.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        payload.extend_from_slice(&last_x.to_le_bytes());
+        payload.extend_from_slice(&last_y.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        packet(RIM_TYPEMOUSE.0, &payload)
+    }
+
+    // RAWKEYBOARD: MakeCode, Flags, Reserved, VKey, Message, ExtraInformation.
+    fn keyboard(make_code: u16, flags: u32, vkey: u16, message: u32) -> Vec<u8> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&make_code.to_le_bytes());
+        payload.extend_from_slice(&(flags as u16).to_le_bytes());
+        payload.extend_from_slice(&0u16.to_le_bytes());
+        payload.extend_from_slice(&vkey.to_le_bytes());
+        payload.extend_from_slice(&message.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        packet(RIM_TYPEKEYBOARD.0, &payload)
+    }
+
+    fn messages(
+        packet: RawInputPacket,
+        cursor_pos: Option<(i32, i32)>,
+    ) -> Vec<(u32, usize, isize)> {
+        let mut messages = Vec::new();
+        packet.to_messages(cursor_pos, |umsg, wparam, lparam| {
+            messages.push((umsg, wparam.0, lparam.0))
+        });
+        messages
+    }
+
+    #[test]
+    fn decodes_mouse_packets() {
+        let packet = decode(&mouse(RI_MOUSE_WHEEL, -120, -3, 7)).unwrap();
+
+        assert_eq!(
+            packet,
+            RawInputPacket::Mouse {
+                last_x: -3,
+                last_y: 7,
+                button_flags: RI_MOUSE_WHEEL as u16,
+                button_data: -120,
+            }
+        );
+        assert!(packet.is_mouse());
+        assert!(packet.has_motion());
+        assert!(!decode(&mouse(0, 0, 0, 0)).unwrap().has_motion());
+    }
+
+    #[test]
+    fn decodes_keyboard_packets() {
+        let packet = decode(&keyboard(0x1E, RI_KEY_BREAK, VK_A.0, WM_KEYUP)).unwrap();
+
+        assert_eq!(
+            packet,
+            RawInputPacket::Keyboard {
+                make_code: 0x1E,
+                flags: RI_KEY_BREAK as u16,
+                vkey: VK_A.0,
+            }
+        );
+        assert!(!packet.is_mouse());
+        assert!(!packet.has_motion());
+    }
+
+    #[test]
+    fn rejects_truncated_and_unknown_packets() {
+        let mouse = mouse(RI_MOUSE_LEFT_BUTTON_DOWN, 0, 1, 1);
+        let keyboard = keyboard(0x1E, RI_KEY_MAKE, VK_A.0, WM_KEYDOWN);
+
+        // The last fields read are lLastY and VKey.
+        assert_eq!(decode(&mouse[..RAW_INPUT_HEADER_SIZE + 19]), None);
+        assert_eq!(decode(&keyboard[..RAW_INPUT_HEADER_SIZE + 7]), None);
+        assert!(decode(&mouse[..RAW_INPUT_HEADER_SIZE + 20]).is_some());
+        assert!(decode(&keyboard[..RAW_INPUT_HEADER_SIZE + 8]).is_some());
+
+        assert_eq!(decode(&[]), None);
+        assert_eq!(decode(&[0, 0]), None);
+        assert_eq!(decode(&packet(2, &[0; 16])), None);
+    }
+
+    #[test]
+    fn mouse_packets_become_button_and_wheel_messages() {
+        let flags = RI_MOUSE_LEFT_BUTTON_DOWN | RI_MOUSE_BUTTON_5_UP | RI_MOUSE_WHEEL;
+        let packet = decode(&mouse(flags, -240, 5, 0)).unwrap();
+
+        assert_eq!(
+            messages(packet, Some((-1, 20))),
+            [
+                (WM_MOUSEMOVE, 0, (20 << 16) | 0xFFFF),
+                (WM_LBUTTONDOWN, 0, 0),
+                (WM_XBUTTONUP, (XBUTTON2 as usize) << 16, 0),
+                (WM_MOUSEWHEEL, (-240i16 as u16 as usize) << 16, 0),
+            ]
+        );
+        assert_eq!(packet.legacy_equivalent(), WM_LBUTTONUP);
+        assert_eq!(messages(packet, None).len(), 3);
+    }
+
+    #[test]
+    fn keyboard_packets_become_key_messages() {
+        let down = decode(&keyboard(0x1D, RI_KEY_MAKE | RI_KEY_E0, 0x11, WM_KEYDOWN)).unwrap();
+        let up = decode(&keyboard(0x1E, RI_KEY_BREAK, VK_A.0, WM_KEYUP)).unwrap();
+        let alt = decode(&keyboard(0x38, RI_KEY_MAKE, VK_MENU.0, WM_SYSKEYDOWN)).unwrap();
+
+        assert_eq!(
+            messages(down, None),
+            [(WM_KEYDOWN, 0x11, 1 | (0x1D << 16) | (1 << 24))]
+        );
+        assert_eq!(
+            messages(up, None),
+            [(
+                WM_KEYUP,
+                VK_A.0 as usize,
+                1 | (0x1E << 16) | (1 << 30) | (1 << 31)
+            )]
+        );
+        assert_eq!(
+            messages(alt, None),
+            [(WM_SYSKEYDOWN, VK_MENU.0 as usize, 1 | (0x38 << 16))]
+        );
+
+        assert_eq!(down.legacy_equivalent(), WM_KEYDOWN);
+        assert_eq!(up.legacy_equivalent(), WM_KEYUP);
+    }
+}
@@ -0,0 +1,494 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    ptr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use imgui::{sys, Context};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+
+pub use crate::renderer::MockRenderEngine;
+use crate::{
+    error::{HudhookError, Result},
+    renderer::{Clock, Pipeline, PipelineMessage, CONTEXT_LOCK},
+    ImguiRenderLoop,
+};
+
+const MAGIC: &[u8; 4] = b"HHRC";
+const VERSION: u8 = 2;
+const TAG_FRAME: u8 = 0;
+const TAG_MESSAGE: u8 = 1;
+
+static RECORDER: Lazy<Mutex<Option<Recorder>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedMessage {
+    pub msg: u32,
+    pub wparam: usize,
+    pub lparam: isize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RecordedFrame {
+    pub hwnd: isize,
+    pub time: Duration,
+    pub messages: Vec<RecordedMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Recording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn windows(&self) -> Vec<isize> {
+        let mut windows = Vec::new();
+        for frame in &self.frames {
+            if !windows.contains(&frame.hwnd) {
+                windows.push(frame.hwnd);
+            }
+        }
+        windows
+    }
+
+    pub fn window(&self, hwnd: isize) -> Self {
+        Self {
+            frames: self
+                .frames
+                .iter()
+                .filter(|frame| frame.hwnd == hwnd)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        Ok(writer.flush()?)
+    }
+
+    pub fn read(mut r: impl Read) -> Result<Self> {
+        let mut header = [0u8; 5];
+        r.read_exact(&mut header)?;
+        // Version 1 recordings predate per-window frames and hold a single window.
+        let version = header[4];
+        if &header[..4] != MAGIC || !(1..=VERSION).contains(&version) {
+            return Err(HudhookError::InvalidRecording(
+                "not a hudhook recording".to_string(),
+            ));
+        }
+
+        let mut frames: Vec<RecordedFrame> = Vec::new();
+        loop {
+            let mut tag = [0u8; 1];
+            match r.read_exact(&mut tag) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+
+            match tag[0] {
+                TAG_FRAME => {
+                    frames.push(RecordedFrame {
+                        hwnd: match version {
+                            1 => 0,
+                            _ => read_u64(&mut r)? as i64 as isize,
+                        },
+                        time: Duration::from_micros(read_u64(&mut r)?),
+                        messages: Vec::new(),
+                    });
+                }
+                TAG_MESSAGE => {
+                    let msg = read_u32(&mut r)?;
+                    let wparam = read_u64(&mut r)? as usize;
+                    let lparam = read_u64(&mut r)? as i64 as isize;
+
+                    let Some(frame) = frames.last_mut() else {
+                        return Err(HudhookError::InvalidRecording(
+                            "message recorded before the first frame".to_string(),
+                        ));
+                    };
+
+                    frame.messages.push(RecordedMessage {
+                        msg,
+                        wparam,
+                        lparam,
+                    });
+                }
+                tag => {
+                    return Err(HudhookError::InvalidRecording(format!(
+                        "unknown record tag {tag}"
+                    )));
+                }
+            }
+        }
+
+        Ok(Self {
+            frames,
+        })
+    }
+
+    pub fn write(&self, mut w: impl Write) -> Result<()> {
+        write_header(&mut w)?;
+        for frame in &self.frames {
+            write_frame(
+                &mut w,
+                frame.hwnd,
+                frame.time,
+                frame.messages.iter().copied(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn write_header(w: &mut impl Write) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION])
+}
+
+fn write_frame(
+    w: &mut impl Write,
+    hwnd: isize,
+    time: Duration,
+    messages: impl Iterator<Item = RecordedMessage>,
+) -> io::Result<()> {
+    w.write_all(&[TAG_FRAME])?;
+    w.write_all(&(hwnd as i64).to_le_bytes())?;
+    w.write_all(&(time.as_micros() as u64).to_le_bytes())?;
+
+    for message in messages {
+        w.write_all(&[TAG_MESSAGE])?;
+        w.write_all(&message.msg.to_le_bytes())?;
+        w.write_all(&(message.wparam as u64).to_le_bytes())?;
+        w.write_all(&(message.lparam as i64).to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+pub fn start_recording(path: impl AsRef<Path>) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_header(&mut writer)?;
+
+    *RECORDER.lock() = Some(Recorder {
+        writer,
+        start: Instant::now(),
+    });
+
+    Ok(())
+}
+
+pub fn stop_recording() -> Result<()> {
+    if let Some(mut recorder) = RECORDER.lock().take() {
+        recorder.writer.flush()?;
+    }
+    Ok(())
+}
+
+pub fn is_recording() -> bool {
+    RECORDER.lock().is_some()
+}
+
+pub(crate) fn record_frame(hwnd: isize, messages: &[PipelineMessage]) {
+    let mut recorder = RECORDER.lock();
+    let Some(r) = recorder.as_mut() else {
+        return;
+    };
+
+    let messages = messages
+        .iter()
+        .map(|&PipelineMessage(msg, wparam, lparam)| RecordedMessage {
+            msg,
+            wparam: wparam.0,
+            lparam: lparam.0,
+        });
+
+    if write_frame(&mut r.writer, hwnd, r.start.elapsed(), messages).is_err() {
+        *recorder = None;
+    }
+}
+
+#[derive(Clone, Default)]
+struct ReplayClock(Arc<AtomicU64>);
+
+impl ReplayClock {
+    fn set(&self, time: Duration) {
+        self.0.store(time.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ReplayClock {
+    fn now(&mut self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::SeqCst))
+    }
+}
+
+pub struct Replay {
+    pipeline: Option<Pipeline<MockRenderEngine>>,
+    recording: Recording,
+    clock: ReplayClock,
+    frame: usize,
+}
+
+impl Replay {
+    pub fn new(
+        recording: Recording,
+        render_loop: impl ImguiRenderLoop + Send + Sync + 'static,
+        display_size: [f32; 2],
+    ) -> Result<Self> {
+        // Frames from different windows can't be fed to a single pipeline.
+        let windows = recording.windows();
+        if windows.len() > 1 {
+            return Err(HudhookError::InvalidRecording(format!(
+                "recording has {} windows, select one with Recording::window",
+                windows.len()
+            )));
+        }
+
+        let _guard = CONTEXT_LOCK.lock();
+
+        let clock = ReplayClock::default();
+        let pipeline = Pipeline::headless(
+            Context::create(),
+            MockRenderEngine::default(),
+            Box::new(render_loop),
+            display_size,
+            clock.clone(),
+        );
+        unsafe { sys::igSetCurrentContext(ptr::null_mut()) };
+
+        Ok(Self {
+            pipeline: Some(pipeline.map_err(|(e, _)| e)?),
+            recording,
+            clock,
+            frame: 0,
+        })
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.recording.frames.len()
+    }
+
+    pub fn engine(&self) -> &MockRenderEngine {
+        self.pipeline.as_ref().unwrap().engine()
+    }
+
+    pub fn step(&mut self) -> Result<bool> {
+        let Some(frame) = self.recording.frames.get(self.frame) else {
+            return Ok(false);
+        };
+        let pipeline = self.pipeline.as_mut().unwrap();
+
+        let _guard = CONTEXT_LOCK.lock();
+        pipeline.make_current();
+
+        self.clock.set(frame.time);
+        for message in &frame.messages {
+            pipeline.push_message(PipelineMessage(
+                message.msg,
+                WPARAM(message.wparam),
+                LPARAM(message.lparam),
+            ));
+        }
+
+        let result = pipeline.prepare_render().and_then(|_| pipeline.render(()));
+        unsafe { sys::igSetCurrentContext(ptr::null_mut()) };
+
+        self.frame += 1;
+        result.map(|_| true)
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        while self.step()? {}
+        Ok(())
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        let _guard = CONTEXT_LOCK.lock();
+        if let Some(pipeline) = self.pipeline.take() {
+            pipeline.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imgui::Ui;
+    use windows::Win32::UI::WindowsAndMessaging::{WM_KEYDOWN, WM_MOUSEMOVE, WM_SIZE};
+
+    use super::*;
+
+    fn message(msg: u32, wparam: usize, lparam: isize) -> RecordedMessage {
+        RecordedMessage {
+            msg,
+            wparam,
+            lparam,
+        }
+    }
+
+    fn frame(hwnd: isize, millis: u64, messages: Vec<RecordedMessage>) -> RecordedFrame {
+        RecordedFrame {
+            hwnd,
+            time: Duration::from_millis(millis),
+            messages,
+        }
+    }
+
+    fn recording() -> Recording {
+        Recording {
+            frames: vec![
+                frame(0x10, 0, vec![message(WM_MOUSEMOVE, 0, 0x0014_000A)]),
+                frame(0x20, 16, vec![]),
+                frame(
+                    0x10,
+                    33,
+                    vec![
+                        message(WM_KEYDOWN, 0x41, 0x001E_0001),
+                        message(u32::MAX, usize::MAX, isize::MIN),
+                    ],
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips_recordings() {
+        let recording = recording();
+
+        let mut buf = Vec::new();
+        recording.write(&mut buf).unwrap();
+
+        assert_eq!(&buf[..5], b"HHRC\x02");
+        assert_eq!(Recording::read(&buf[..]).unwrap(), recording);
+    }
+
+    #[test]
+    fn reads_version_1_recordings() {
+        let mut buf = b"HHRC\x01".to_vec();
+        buf.push(TAG_FRAME);
+        buf.extend(1000u64.to_le_bytes());
+        buf.push(TAG_MESSAGE);
+        buf.extend(WM_MOUSEMOVE.to_le_bytes());
+        buf.extend(0u64.to_le_bytes());
+        buf.extend(0x0014_000Ai64.to_le_bytes());
+
+        assert_eq!(
+            Recording::read(&buf[..]).unwrap(),
+            Recording {
+                frames: vec![RecordedFrame {
+                    hwnd: 0,
+                    time: Duration::from_millis(1),
+                    messages: vec![message(WM_MOUSEMOVE, 0, 0x0014_000A)],
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_recordings() {
+        let invalid =
+            |buf: &[u8]| matches!(Recording::read(buf), Err(HudhookError::InvalidRecording(_)));
+
+        assert!(invalid(b"HHRX\x02"));
+        assert!(invalid(b"HHRC\x03"));
+        assert!(invalid(b"HHRC\x02\x07"));
+        assert!(invalid(
+            &[b"HHRC\x02".as_slice(), &[TAG_MESSAGE], &[0; 20]].concat()
+        ));
+        assert!(Recording::read(&b"HHRC\x02\x00\x01"[..]).is_err());
+    }
+
+    #[test]
+    fn splits_recordings_by_window() {
+        let recording = recording();
+
+        assert_eq!(recording.windows(), [0x10, 0x20]);
+        assert_eq!(recording.window(0x10).frames.len(), 2);
+        assert_eq!(recording.window(0x20).frames, [frame(0x20, 16, vec![])]);
+        assert!(recording.window(0x30).frames.is_empty());
+    }
+
+    struct MousePositions(Arc<Mutex<Vec<[f32; 2]>>>);
+
+    impl ImguiRenderLoop for MousePositions {
+        unsafe fn render(&mut self, ui: &mut Ui) {
+            self.0.lock().push(ui.io().mouse_pos);
+            ui.text("replay");
+        }
+    }
+
+    #[test]
+    fn replays_frames_into_the_render_loop() {
+        let positions = Arc::new(Mutex::new(Vec::new()));
+        let recording = Recording {
+            frames: vec![
+                frame(0x10, 0, vec![message(WM_MOUSEMOVE, 0, 0x0014_000A)]),
+                frame(0x10, 16, vec![message(WM_SIZE, 0, 0x0258_0320)]),
+                frame(0x10, 33, vec![message(WM_MOUSEMOVE, 0, 0x0028_001E)]),
+            ],
+        };
+
+        let mut replay = Replay::new(
+            recording,
+            MousePositions(Arc::clone(&positions)),
+            [640., 480.],
+        )
+        .unwrap();
+        replay.run().unwrap();
+
+        assert!(replay.is_finished());
+        assert_eq!(replay.frame(), 3);
+        assert_eq!(replay.engine().frames_rendered(), 3);
+        assert!(replay.engine().vertex_count() > 0);
+        assert_eq!(*positions.lock(), [[10., 20.], [10., 20.], [30., 40.]]);
+        assert!(!replay.step().unwrap());
+    }
+
+    #[test]
+    fn refuses_to_replay_several_windows() {
+        let result = Replay::new(
+            recording(),
+            MousePositions(Default::default()),
+            [640., 480.],
+        );
+
+        assert!(matches!(result, Err(HudhookError::InvalidRecording(_))));
+    }
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+#[cfg(windows)]
+use std::{ffi::c_void, mem, slice};
+
+#[cfg(windows)]
+use windows::Win32::System::{
+    Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot,
+        Module32FirstW,
+        Module32NextW,
+        MODULEENTRY32W,
+        TH32CS_SNAPMODULE,
+        TH32CS_SNAPMODULE32,
+    },
+    Memory::{
+        VirtualQuery,
+        MEMORY_BASIC_INFORMATION,
+        MEM_COMMIT,
+        PAGE_EXECUTE_READ,
+        PAGE_EXECUTE_READWRITE,
+        PAGE_EXECUTE_WRITECOPY,
+        PAGE_GUARD,
+        PAGE_NOACCESS,
+    },
+};
+
+use crate::error::{HudhookError, Result};
+#[cfg(windows)]
+use crate::inject::Handle;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern(Vec<Option<u8>>);
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let bytes = pattern
+            .split_whitespace()
+            .map(|token| match token {
+                "?" | "??" => Ok(None),
+                _ if token.len() == 2 && token.bytes().all(|c| c.is_ascii_hexdigit()) => {
+                    Ok(u8::from_str_radix(token, 16).ok())
+                }
+                _ => Err(HudhookError::InvalidPattern(format!(
+                    "invalid byte {token:?}"
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if bytes.iter().all(Option::is_none) {
+            return Err(HudhookError::InvalidPattern(
+                "pattern has no fixed bytes".to_string(),
+            ));
+        }
+
+        Ok(Self(bytes))
+    }
+
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() >= self.0.len()
+            && self
+                .0
+                .iter()
+                .zip(bytes)
+                .all(|(pattern, &byte)| pattern.is_none_or(|pattern| pattern == byte))
+    }
+
+    pub fn find_all<'a>(&'a self, haystack: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        haystack
+            .windows(self.0.len())
+            .enumerate()
+            .filter(|(_, window)| self.matches(window))
+            .map(|(offset, _)| offset)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            match byte {
+                Some(byte) => write!(f, "{byte:02X}")?,
+                None => f.write_str("??")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Describes an instruction at a match whose operand is a 32-bit displacement from the end of the
+// instruction, e.g. `E8 <disp>` has `disp_offset: 1, instr_len: 5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RipRelative {
+    pub disp_offset: usize,
+    pub instr_len: usize,
+}
+
+impl RipRelative {
+    // The target is returned relative to the start of `bytes` and may lie outside of it.
+    pub fn target(&self, bytes: &[u8], at: usize) -> Option<isize> {
+        let start = at.checked_add(self.disp_offset)?;
+        let disp = bytes.get(start..start.checked_add(4)?)?;
+        let disp = i32::from_le_bytes(disp.try_into().ok()?);
+
+        isize::try_from(at.checked_add(self.instr_len)?)
+            .ok()?
+            .checked_add(disp as isize)
+    }
+}
+
+#[cfg(windows)]
+pub fn scan_module(
+    module: &str,
+    pattern: &Pattern,
+    rip: Option<RipRelative>,
+) -> Result<Vec<*mut c_void>> {
+    let (base, size) = find_module(module)?;
+
+    let mut matches = Vec::new();
+    for (start, end) in executable_ranges(base, size) {
+        let bytes = unsafe { slice::from_raw_parts(start as *const u8, end - start) };
+
+        for at in pattern.find_all(bytes) {
+            let addr = match rip {
+                None => start + at,
+                Some(rip) => match rip.target(bytes, at) {
+                    Some(target) => start.wrapping_add_signed(target),
+                    None => continue,
+                },
+            };
+            matches.push(addr as *mut c_void);
+        }
+    }
+
+    Ok(matches)
+}
+
+#[cfg(windows)]
+pub fn find_unique(
+    module: &str,
+    pattern: &Pattern,
+    rip: Option<RipRelative>,
+) -> Result<*mut c_void> {
+    match scan_module(module, pattern, rip)?[..] {
+        [addr] => Ok(addr),
+        [] => Err(HudhookError::PatternNotFound {
+            module: module.to_string(),
+            pattern: pattern.to_string(),
+        }),
+        ref matches => Err(HudhookError::AmbiguousPattern {
+            pattern: pattern.to_string(),
+            matches: matches.len(),
+        }),
+    }
+}
+
+#[cfg(windows)]
+fn find_module(module: &str) -> Result<(usize, usize)> {
+    // A process id of 0 snapshots the current process.
+    let snapshot =
+        Handle(unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, 0) }?);
+
+    let mut entry = MODULEENTRY32W {
+        dwSize: mem::size_of::<MODULEENTRY32W>() as u32,
+        ..Default::default()
+    };
+
+    let mut next = unsafe { Module32FirstW(snapshot.0, &mut entry) };
+    while next.is_ok() {
+        let len = entry
+            .szModule
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(entry.szModule.len());
+        if String::from_utf16_lossy(&entry.szModule[..len]).eq_ignore_ascii_case(module) {
+            return Ok((entry.modBaseAddr as usize, entry.modBaseSize as usize));
+        }
+        next = unsafe { Module32NextW(snapshot.0, &mut entry) };
+    }
+
+    Err(HudhookError::ModuleNotLoaded(module.to_string()))
+}
+
+// Adjacent regions are merged so that patterns spanning a region boundary are still found.
+#[cfg(windows)]
+fn executable_ranges(base: usize, size: usize) -> Vec<(usize, usize)> {
+    let end = base + size;
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    let mut addr = base;
+    while addr < end {
+        let mut info = MEMORY_BASIC_INFORMATION::default();
+        let written = unsafe {
+            VirtualQuery(
+                Some(addr as *const c_void),
+                &mut info,
+                mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+            )
+        };
+        if written == 0 {
+            break;
+        }
+
+        let region_end = (info.BaseAddress as usize + info.RegionSize).min(end);
+        if is_readable_code(&info) {
+            match ranges.last_mut() {
+                Some((_, range_end)) if *range_end == addr => *range_end = region_end,
+                _ => ranges.push((addr, region_end)),
+            }
+        }
+
+        addr = region_end;
+    }
+
+    ranges
+}
+
+#[cfg(windows)]
+fn is_readable_code(info: &MEMORY_BASIC_INFORMATION) -> bool {
+    let readable_code = PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY;
+
+    info.State == MEM_COMMIT
+        && (info.Protect & (PAGE_GUARD | PAGE_NOACCESS)).0 == 0
+        && (info.Protect & readable_code).0 != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bytes_and_wildcards() {
+        let pattern = Pattern::parse("48 8b ? ?? E8").unwrap();
+
+        assert_eq!(
+            pattern,
+            Pattern(vec![Some(0x48), Some(0x8B), None, None, Some(0xE8)])
+        );
+        assert_eq!(pattern.to_string(), "48 8B ?? ?? E8");
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        for pattern in ["", "?? ??", "4", "488B", "+F", "GG", "48 ???"] {
+            assert!(
+                matches!(
+                    Pattern::parse(pattern),
+                    Err(HudhookError::InvalidPattern(_))
+                ),
+                "{pattern:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn wildcards_match_any_byte() {
+        let pattern = Pattern::parse("48 ?? E8").unwrap();
+
+        assert!(pattern.matches(&[0x48, 0x00, 0xE8]));
+        assert!(pattern.matches(&[0x48, 0xFF, 0xE8, 0x90]));
+        assert!(!pattern.matches(&[0x48, 0x00, 0xE9]));
+        assert!(!pattern.matches(&[0x48, 0x00]));
+    }
+
+    #[test]
+    fn finds_all_matches() {
+        let pattern = Pattern::parse("48 8B ?? ?? E8").unwrap();
+        let haystack = [
+            0x00, 0x48, 0x8B, 0x01, 0x02, 0xE8, 0x48, 0x8B, 0x03, 0x04, 0xE8, 0x48,
+        ];
+
+        assert_eq!(pattern.find_all(&haystack).collect::<Vec<_>>(), [1, 6]);
+        assert_eq!(pattern.find_all(&haystack[..10]).count(), 1);
+        assert_eq!(pattern.find_all(&[]).count(), 0);
+    }
+
+    #[test]
+    fn finds_overlapping_matches() {
+        let pattern = Pattern::parse("90 ?? 90").unwrap();
+
+        assert_eq!(pattern.find_all(&[0x90; 5]).collect::<Vec<_>>(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn resolves_rip_relative_targets() {
+        // call -5 (to itself) at offset 1, then lea rax, [rip + 0x10] at offset 6.
+        let code = [
+            0x90, 0xE8, 0xFB, 0xFF, 0xFF, 0xFF, 0x48, 0x8D, 0x05, 0x10, 0x00, 0x00, 0x00,
+        ];
+        let call = RipRelative {
+            disp_offset: 1,
+            instr_len: 5,
+        };
+        let lea = RipRelative {
+            disp_offset: 3,
+            instr_len: 7,
+        };
+
+        assert_eq!(call.target(&code, 1), Some(1));
+        assert_eq!(lea.target(&code, 6), Some(6 + 7 + 0x10));
+    }
+
+    #[test]
+    fn rip_relative_target_needs_the_whole_displacement() {
+        let call = RipRelative {
+            disp_offset: 1,
+            instr_len: 5,
+        };
+
+        assert_eq!(call.target(&[0xE8, 0x00, 0x00, 0x00], 0), None);
+        assert_eq!(call.target(&[0xE8, 0x00, 0x00, 0x00, 0x00], 0), Some(5));
+        assert_eq!(call.target(&[0xE8], usize::MAX), None);
+    }
+}
@@ -1,25 +1,33 @@
 use std::{
     mem::ManuallyDrop,
-    sync::atomic::{AtomicU64, Ordering},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
 };
 
+use crate::error::HudhookError;
+
 use windows::Win32::{
-    Foundation::{HANDLE, HWND, RECT},
-    Graphics::Direct3D12::{
-        ID3D12Device,
-        ID3D12Fence,
-        ID3D12Resource,
-        D3D12_FENCE_FLAG_NONE,
-        D3D12_RESOURCE_BARRIER,
-        D3D12_RESOURCE_BARRIER_0,
-        D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-        D3D12_RESOURCE_BARRIER_FLAG_NONE,
-        D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
-        D3D12_RESOURCE_STATES,
-        D3D12_RESOURCE_TRANSITION_BARRIER,
+    Foundation::{HANDLE, HWND, POINT, RECT},
+    Graphics::{
+        Direct3D12::{
+            ID3D12Device,
+            ID3D12Fence,
+            ID3D12Resource,
+            D3D12_FENCE_FLAG_NONE,
+            D3D12_RESOURCE_BARRIER,
+            D3D12_RESOURCE_BARRIER_0,
+            D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
+            D3D12_RESOURCE_BARRIER_FLAG_NONE,
+            D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
+            D3D12_RESOURCE_STATES,
+            D3D12_RESOURCE_TRANSITION_BARRIER,
+        },
+        Gdi::ScreenToClient,
     },
     System::Threading::{CreateEventExW, WaitForSingleObjectEx, CREATE_EVENT},
-    UI::WindowsAndMessaging::GetClientRect,
+    UI::{
+        HiDpi::GetDpiForWindow,
+        WindowsAndMessaging::{GetClientRect, GetCursorPos, USER_DEFAULT_SCREEN_DPI},
+    },
 };
 
 pub fn try_out_param<T, F, E, O>(mut f: F) -> Result<T, E>
@@ -66,10 +74,26 @@ where
     val
 }
 
-pub fn win_size(hwnd: HWND) -> (i32, i32) {
+pub fn win_size(hwnd: HWND) -> Result<(i32, i32), HudhookError> {
     let mut rect = RECT::default();
-    unsafe { GetClientRect(hwnd, &mut rect).unwrap() };
-    (rect.right - rect.left, rect.bottom - rect.top)
+    unsafe { GetClientRect(hwnd, &mut rect)? };
+    Ok((rect.right - rect.left, rect.bottom - rect.top))
+}
+
+pub fn dpi_scale(hwnd: HWND) -> f32 {
+    match unsafe { GetDpiForWindow(hwnd) } {
+        0 => 1.0,
+        dpi => dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32,
+    }
+}
+
+pub fn cursor_pos(hwnd: HWND) -> Option<(i32, i32)> {
+    let mut point = POINT::default();
+    unsafe {
+        GetCursorPos(&mut point).ok()?;
+        ScreenToClient(hwnd, &mut point).ok().ok()?;
+    }
+    Some((point.x, point.y))
 }
 
 pub fn create_barrier(
@@ -103,7 +127,7 @@ pub struct Fence {
 }
 
 impl Fence {
-    pub fn new(device: &ID3D12Device) -> windows::core::Result<Self> {
+    pub fn new(device: &ID3D12Device) -> Result<Self, HudhookError> {
         let fence = unsafe { device.CreateFence(0, D3D12_FENCE_FLAG_NONE) }?;
         let value = AtomicU64::new(0);
         let event = unsafe { CreateEventExW(None, None, CREATE_EVENT(0), 0x1F0003) }?;
@@ -127,7 +151,7 @@ impl Fence {
         self.value.fetch_add(1, Ordering::SeqCst);
     }
 
-    pub fn wait(&self) -> windows::core::Result<()> {
+    pub fn wait(&self) -> Result<(), HudhookError> {
         let value = self.value();
         unsafe {
             if self.fence.GetCompletedValue() < value {
@@ -139,3 +163,29 @@ impl Fence {
         Ok(())
     }
 }
+
+// Counts threads currently executing one of our detours, so the module is not unmapped under them.
+pub(crate) struct InFlight(AtomicUsize);
+
+impl InFlight {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+
+    pub(crate) fn enter(&self) -> InFlightGuard<'_> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self)
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+pub(crate) struct InFlightGuard<'a>(&'a InFlight);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0 .0.fetch_sub(1, Ordering::SeqCst);
+    }
+}